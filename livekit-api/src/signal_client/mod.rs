@@ -1,21 +1,28 @@
 use crate::signal_client::signal_stream::SignalStream;
 use livekit_protocol as proto;
 use parking_lot::Mutex;
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc;
+use tokio::sync::Mutex as AsyncMutex;
 use tokio::sync::RwLock as AsyncRwLock;
 use tokio_tungstenite::tungstenite::Error as WsError;
 
+pub mod region;
 mod signal_stream;
 
 pub type SignalEmitter = mpsc::Sender<SignalEvent>;
 pub type SignalEvents = mpsc::Receiver<SignalEvent>;
 pub type SignalResult<T> = Result<T, SignalError>;
 
+pub const SIGNAL_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 pub const JOIN_RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
 pub const PROTOCOL_VERSION: u32 = 8;
+pub const MAX_QUEUED_REQUESTS: usize = 64;
 
 #[derive(Error, Debug)]
 pub enum SignalError {
@@ -27,8 +34,19 @@ pub enum SignalError {
     UrlParse(#[from] url::ParseError),
     #[error("failed to decode messages from server")]
     ProtoParse(#[from] prost::DecodeError),
+    /// Not returned by the SignalClient anymore, see ConnectTimeout and JoinTimeout
     #[error("{0}")]
     Timeout(String),
+    #[error("timed out while connecting to the websocket")]
+    ConnectTimeout,
+    #[error("failed to receive JoinResponse")]
+    JoinTimeout,
+    #[error("failed to send the request: {0}")]
+    SendError(String),
+    #[error("the signal queue is full")]
+    QueueFull,
+    #[error("failed to fetch the regions: {0}")]
+    RegionError(String),
 }
 
 /// Events used by the RTCEngine who will handle the reconnection logic
@@ -43,6 +61,10 @@ pub enum SignalEvent {
 pub struct SignalOptions {
     pub auto_subscribe: bool,
     pub adaptive_stream: bool,
+    /// Timeout of the websocket handshake (also used when resuming)
+    pub connect_timeout: Duration,
+    /// Time to wait for the JoinResponse once the websocket is open
+    pub join_timeout: Duration,
 }
 
 impl Default for SignalOptions {
@@ -50,6 +72,8 @@ impl Default for SignalOptions {
         Self {
             auto_subscribe: true,
             adaptive_stream: false,
+            connect_timeout: SIGNAL_CONNECT_TIMEOUT,
+            join_timeout: JOIN_RESPONSE_TIMEOUT,
         }
     }
 }
@@ -58,31 +82,133 @@ impl Default for SignalOptions {
 pub struct SignalClient {
     stream: AsyncRwLock<Option<SignalStream>>,
     url: String,
-    token: Mutex<String>, // TODO(theomonnom): Handle token refresh
+    region: Option<String>,
+    token: Mutex<String>, // Updated with the refreshed tokens sent by the server
     join_response: proto::JoinResponse,
     options: SignalOptions,
     emitter: SignalEmitter,
+
+    // Requests sent while resuming are queued and sent after the reconnection,
+    // also set by the SignalStream as soon as the connection is lost
+    reconnecting: Arc<AtomicBool>,
+    queue: AsyncMutex<VecDeque<proto::signal_request::Message>>,
 }
 
 impl SignalClient {
+    /// Connect to `url`, on LiveKit Cloud the other regions of the project are tried
+    /// when the connection fails
     pub async fn connect(
         url: &str,
         token: &str,
         options: SignalOptions,
+    ) -> SignalResult<(Self, proto::JoinResponse, SignalEvents)> {
+        match Self::connect_url(url, token, options.clone(), None).await {
+            Err(err)
+                if is_region_fallback_error(&err)
+                    && region::is_cloud_url(&url::Url::parse(url)?) =>
+            {
+                log::warn!(
+                    "failed to connect to {}: {}, trying the other regions",
+                    url,
+                    err
+                );
+                Self::connect_regions(url, token, options, err).await
+            }
+            res => res,
+        }
+    }
+
+    /// Try the regions of the project from the closest to the farthest,
+    /// `err` is returned if the regions can't be fetched
+    async fn connect_regions(
+        url: &str,
+        token: &str,
+        options: SignalOptions,
+        err: SignalError,
+    ) -> SignalResult<(Self, proto::JoinResponse, SignalEvents)> {
+        let regions = match region::fetch_regions(url, token, options.connect_timeout).await {
+            Ok(regions) => regions,
+            Err(region_err) => {
+                log::error!("{}", region_err);
+                return Err(err);
+            }
+        };
+
+        // The url already tried, it may use another scheme than the one of the regions
+        let initial_url = region::ws_url(url)?;
+        let mut last_err = err;
+        for region in regions {
+            let region_url = match region::ws_url(&region.url) {
+                Ok(region_url) => region_url,
+                Err(err) => {
+                    log::warn!("invalid url for the region {}: {}", region.region, err);
+                    continue;
+                }
+            };
+
+            if is_same_server(&region_url, &initial_url) {
+                continue;
+            }
+
+            log::info!("connecting to the region {}: {}", region.region, region_url);
+            match Self::connect_url(
+                region_url.as_str(),
+                token,
+                options.clone(),
+                Some(region.region),
+            )
+            .await
+            {
+                Ok(res) => return Ok(res),
+                Err(err) if is_region_fallback_error(&err) => {
+                    log::warn!("failed to connect to {}: {}", region_url, err);
+                    last_err = err;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn connect_url(
+        url: &str,
+        token: &str,
+        options: SignalOptions,
+        region: Option<String>,
     ) -> SignalResult<(Self, proto::JoinResponse, SignalEvents)> {
         let (emitter, mut events) = mpsc::channel(8);
+        let reconnecting = Arc::new(AtomicBool::new(false));
         let lk_url = get_livekit_url(url, token, &options)?;
-        let new_stream = SignalStream::connect(lk_url, emitter.clone()).await?;
-        let join_response = get_join_response(&mut events).await?;
+        let new_stream = connect_stream(
+            lk_url,
+            emitter.clone(),
+            reconnecting.clone(),
+            options.connect_timeout,
+        )
+        .await?;
+        let join_response = get_join_response(&mut events, options.join_timeout).await?;
+
+        // Without fallback, the region is the one reported by the server
+        let region = region.or_else(|| {
+            join_response
+                .server_info
+                .as_ref()
+                .map(|server_info| server_info.region.clone())
+                .filter(|region| !region.is_empty())
+        });
 
         Ok((
             Self {
                 stream: AsyncRwLock::new(Some(new_stream)),
                 url: url.to_string(),
+                region,
                 token: Mutex::new(token.to_string()),
                 join_response: join_response.clone(),
                 options,
                 emitter,
+                reconnecting,
+                queue: Default::default(),
             },
             join_response,
             events,
@@ -91,7 +217,9 @@ impl SignalClient {
 
     // Restart is called when trying to resume the room (RtcSession resume)
     // TODO(theomonom): Should this be renamed to resume?
+    // The queued requests are only sent after calling flush_queue
     pub async fn restart(&self) -> SignalResult<()> {
+        self.reconnecting.store(true, Ordering::Release);
         self.close().await;
 
         let sid = &self.join_response.participant.as_ref().unwrap().sid;
@@ -103,7 +231,13 @@ impl SignalClient {
             .append_pair("reconnect", "1")
             .append_pair("sid", sid);
 
-        let new_stream = SignalStream::connect(lk_url, self.emitter.clone()).await?;
+        let new_stream = connect_stream(
+            lk_url,
+            self.emitter.clone(),
+            self.reconnecting.clone(),
+            self.options.connect_timeout,
+        )
+        .await?;
         *self.stream.write().await = Some(new_stream);
         Ok(())
     }
@@ -114,24 +248,56 @@ impl SignalClient {
         }
     }
 
-    pub async fn send(&self, signal: proto::signal_request::Message) {
-        // TODO: Check if currently reconnecting and queue message
+    /// Send a SignalRequest to the server
+    /// Once the connection is lost and while resuming, the requests that can't
+    /// bypass the queue are sent after [SignalClient::flush_queue] is called
+    pub async fn send(&self, signal: proto::signal_request::Message) -> SignalResult<()> {
+        if !is_queue_bypassed(&signal) {
+            // Lock the queue before checking the flag so we can't race with flush_queue
+            let mut queue = self.queue.lock().await;
+            if self.reconnecting.load(Ordering::Acquire) {
+                if queue.len() >= MAX_QUEUED_REQUESTS {
+                    return Err(SignalError::QueueFull);
+                }
 
-        if let Some(stream) = self.stream.read().await.as_ref() {
-            if stream.send(signal).await.is_ok() {
-                return;
+                log::debug!("queuing SignalRequest: {:?}", signal);
+                queue.push_back(signal);
+                return Ok(());
             }
         }
-        // TODO(theomonnom): return result?
+
+        self.send_now(signal).await
     }
 
-    #[allow(dead_code)]
+    /// Discard the queued requests (e.g when doing a full reconnect, they are
+    /// meaningless for a new session)
     pub async fn clear_queue(&self) {
-        // TODO(theomonnom): Clear the queue
+        let mut queue = self.queue.lock().await;
+        queue.clear();
+        self.reconnecting.store(false, Ordering::Release);
     }
 
-    pub async fn flush_queue(&self) {
-        // TODO(theomonnom): Send the queue
+    /// Send the queued requests in order, this must be called once the session
+    /// is successfully resumed
+    pub async fn flush_queue(&self) -> SignalResult<()> {
+        let mut queue = self.queue.lock().await;
+        self.reconnecting.store(false, Ordering::Release);
+        while let Some(signal) = queue.pop_front() {
+            if let Err(err) = self.send_now(signal).await {
+                queue.clear();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_now(&self, signal: proto::signal_request::Message) -> SignalResult<()> {
+        let stream = self.stream.read().await;
+        let Some(stream) = stream.as_ref() else {
+            return Err(SignalError::SendError("not connected".to_string()));
+        };
+
+        stream.send(signal).await
     }
 
     pub fn join_response(&self) -> proto::JoinResponse {
@@ -146,13 +312,51 @@ impl SignalClient {
         self.url.clone()
     }
 
+    /// Region of the server, when known
+    pub fn region(&self) -> Option<String> {
+        self.region.clone()
+    }
+
     pub fn token(&self) -> String {
         self.token.lock().clone()
     }
+
+    /// Store a token refreshed by the server, it is used for the next reconnections
+    pub fn set_token(&self, token: String) {
+        *self.token.lock() = token;
+    }
+}
+
+/// The other regions are only tried when the server can't be reached
+/// (e.g. an invalid token is rejected by every region)
+fn is_region_fallback_error(err: &SignalError) -> bool {
+    match err {
+        SignalError::WsError(WsError::Http(res)) => !res.status().is_client_error(),
+        SignalError::WsError(_) | SignalError::ConnectTimeout | SignalError::JoinTimeout => true,
+        _ => false,
+    }
+}
+
+/// Requests that are only meaningful for the current connection are never queued
+/// (See canPassThroughQueue in the JS SDK)
+fn is_queue_bypassed(signal: &proto::signal_request::Message) -> bool {
+    matches!(
+        signal,
+        proto::signal_request::Message::SyncState(_)
+            | proto::signal_request::Message::Trickle(_)
+            | proto::signal_request::Message::Offer(_)
+            | proto::signal_request::Message::Answer(_)
+            | proto::signal_request::Message::Simulate(_)
+            | proto::signal_request::Message::Leave(_)
+    )
+}
+
+fn is_same_server(a: &url::Url, b: &url::Url) -> bool {
+    a.host_str() == b.host_str() && a.port_or_known_default() == b.port_or_known_default()
 }
 
 fn get_livekit_url(url: &str, token: &str, options: &SignalOptions) -> SignalResult<url::Url> {
-    let mut lk_url = url::Url::parse(url)?;
+    let mut lk_url = region::ws_url(url)?;
     lk_url.set_path("/rtc");
     lk_url
         .query_pairs_mut()
@@ -170,7 +374,24 @@ fn get_livekit_url(url: &str, token: &str, options: &SignalOptions) -> SignalRes
     Ok(lk_url)
 }
 
-async fn get_join_response(receiver: &mut SignalEvents) -> SignalResult<proto::JoinResponse> {
+async fn connect_stream(
+    url: url::Url,
+    emitter: SignalEmitter,
+    reconnecting: Arc<AtomicBool>,
+    connect_timeout: Duration,
+) -> SignalResult<SignalStream> {
+    tokio::time::timeout(
+        connect_timeout,
+        SignalStream::connect(url, emitter, reconnecting),
+    )
+    .await
+    .map_err(|_| SignalError::ConnectTimeout)?
+}
+
+async fn get_join_response(
+    receiver: &mut SignalEvents,
+    join_timeout: Duration,
+) -> SignalResult<proto::JoinResponse> {
     let join = async {
         while let Some(event) = receiver.recv().await {
             match event {
@@ -192,7 +413,197 @@ async fn get_join_response(receiver: &mut SignalEvents) -> SignalResult<proto::J
         Err(WsError::ConnectionClosed)?
     };
 
-    tokio::time::timeout(JOIN_RESPONSE_TIMEOUT, join)
+    tokio::time::timeout(join_timeout, join)
         .await
-        .map_err(|_| SignalError::Timeout("failed to receive JoinResponse".to_string()))?
+        .map_err(|_| SignalError::JoinTimeout)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use prost::Message as ProstMessage;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::WebSocketStream;
+
+    /// Returns a client connected to a local websocket and the server side of the connection
+    async fn connected_client() -> (SignalClient, WebSocketStream<TcpStream>, SignalEvents) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let (emitter, events) = mpsc::channel(8);
+        let reconnecting = Arc::new(AtomicBool::new(false));
+        let (stream, server) = tokio::join!(
+            SignalStream::connect(
+                url::Url::parse(&url).unwrap(),
+                emitter.clone(),
+                reconnecting.clone()
+            ),
+            async {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio_tungstenite::accept_async(stream).await.unwrap()
+            }
+        );
+
+        let client = SignalClient {
+            stream: AsyncRwLock::new(Some(stream.unwrap())),
+            url,
+            region: None,
+            token: Mutex::new("mytoken".to_owned()),
+            join_response: proto::JoinResponse::default(),
+            options: SignalOptions::default(),
+            emitter,
+            reconnecting,
+            queue: Default::default(),
+        };
+
+        (client, server, events)
+    }
+
+    async fn recv_request(
+        server: &mut WebSocketStream<TcpStream>,
+    ) -> proto::signal_request::Message {
+        let Some(Ok(Message::Binary(data))) = server.next().await else {
+            panic!("expected a binary message");
+        };
+
+        proto::SignalRequest::decode(data.as_slice())
+            .unwrap()
+            .message
+            .unwrap()
+    }
+
+    fn mute_request(sid: &str) -> proto::signal_request::Message {
+        proto::signal_request::Message::Mute(proto::MuteTrackRequest {
+            sid: sid.to_owned(),
+            muted: true,
+        })
+    }
+
+    fn leave_request() -> proto::signal_request::Message {
+        proto::signal_request::Message::Leave(proto::LeaveRequest::default())
+    }
+
+    #[test]
+    fn test_is_same_server() {
+        let same = |a: &str, b: &str| {
+            is_same_server(&region::ws_url(a).unwrap(), &region::ws_url(b).unwrap())
+        };
+
+        assert!(same(
+            "wss://test.livekit.cloud",
+            "https://test.livekit.cloud/"
+        ));
+        assert!(same(
+            "wss://test.livekit.cloud:443",
+            "wss://test.livekit.cloud"
+        ));
+        assert!(!same(
+            "wss://test.livekit.cloud",
+            "wss://other.livekit.cloud"
+        ));
+        assert!(!same("ws://localhost:7880", "ws://localhost:7881"));
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let (client, mut server, _events) = connected_client().await;
+        client.send(mute_request("TR_1")).await.unwrap();
+        assert_eq!(recv_request(&mut server).await, mute_request("TR_1"));
+        assert!(client.queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_queue_and_flush() {
+        let (client, mut server, _events) = connected_client().await;
+        client.reconnecting.store(true, Ordering::Release);
+
+        client.send(mute_request("TR_1")).await.unwrap();
+        client.send(mute_request("TR_2")).await.unwrap();
+        assert_eq!(client.queue.lock().await.len(), 2);
+
+        // Bypass the queue
+        client.send(leave_request()).await.unwrap();
+        assert_eq!(recv_request(&mut server).await, leave_request());
+
+        // Sent in order
+        client.flush_queue().await.unwrap();
+        assert_eq!(recv_request(&mut server).await, mute_request("TR_1"));
+        assert_eq!(recv_request(&mut server).await, mute_request("TR_2"));
+        assert!(client.queue.lock().await.is_empty());
+
+        // Not queued anymore
+        client.send(mute_request("TR_3")).await.unwrap();
+        assert_eq!(recv_request(&mut server).await, mute_request("TR_3"));
+    }
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let (client, _server, _events) = connected_client().await;
+        client.reconnecting.store(true, Ordering::Release);
+
+        for i in 0..MAX_QUEUED_REQUESTS {
+            client.send(mute_request(&i.to_string())).await.unwrap();
+        }
+
+        assert!(matches!(
+            client.send(mute_request("TR_1")).await,
+            Err(SignalError::QueueFull)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_clear_queue() {
+        let (client, mut server, _events) = connected_client().await;
+        client.reconnecting.store(true, Ordering::Release);
+
+        client.send(mute_request("TR_1")).await.unwrap();
+        client.clear_queue().await;
+        assert!(client.queue.lock().await.is_empty());
+
+        // The discarded requests are never sent
+        client.flush_queue().await.unwrap();
+        client.send(mute_request("TR_2")).await.unwrap();
+        assert_eq!(recv_request(&mut server).await, mute_request("TR_2"));
+    }
+
+    #[tokio::test]
+    async fn test_flush_failure() {
+        let (client, _server, _events) = connected_client().await;
+        client.reconnecting.store(true, Ordering::Release);
+
+        client.send(mute_request("TR_1")).await.unwrap();
+        client.close().await;
+
+        assert!(client.flush_queue().await.is_err());
+        assert!(client.queue.lock().await.is_empty());
+        assert!(!client.reconnecting.load(Ordering::Acquire));
+    }
+
+    #[tokio::test]
+    async fn test_queue_when_connection_lost() {
+        let (client, mut server, mut events) = connected_client().await;
+        server.close(None).await.unwrap();
+
+        while let Some(event) = events.recv().await {
+            if matches!(event, SignalEvent::Close) {
+                break;
+            }
+        }
+
+        // Queued until the client is resumed
+        assert!(client.reconnecting.load(Ordering::Acquire));
+        client.send(mute_request("TR_1")).await.unwrap();
+        assert_eq!(client.queue.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_does_not_queue() {
+        let (client, _server, _events) = connected_client().await;
+        client.close().await;
+
+        assert!(!client.reconnecting.load(Ordering::Acquire));
+        assert!(client.send(mute_request("TR_1")).await.is_err());
+    }
 }
@@ -0,0 +1,311 @@
+use super::{SignalError, SignalResult};
+use reqwest::header::AUTHORIZATION;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// A region of LiveKit Cloud able to host the room
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RegionInfo {
+    pub region: String,
+    pub url: String,
+    /// Distance to the client, the regions are tried from the closest to the farthest
+    #[serde(default, deserialize_with = "deserialize_distance")]
+    pub distance: i64,
+}
+
+#[derive(Deserialize)]
+struct RegionSettings {
+    regions: Vec<RegionInfo>,
+}
+
+// int64 fields are encoded as strings by the protobuf JSON mapping
+fn deserialize_distance<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Distance {
+        Number(i64),
+        String(String),
+    }
+
+    match Distance::deserialize(deserializer)? {
+        Distance::Number(distance) => Ok(distance),
+        Distance::String(distance) => distance.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Only LiveKit Cloud exposes the regions of a project
+pub fn is_cloud_url(url: &url::Url) -> bool {
+    url.host_str().map_or(false, |host| {
+        host.ends_with(".livekit.cloud") || host.ends_with(".livekit.run")
+    })
+}
+
+/// Websocket url of a server, the regions endpoint returns http(s) urls
+pub fn ws_url(url: &str) -> SignalResult<url::Url> {
+    let mut url = url::Url::parse(url)?;
+    let scheme = match url.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        _ => return Ok(url),
+    };
+
+    // Switching between special schemes can't fail
+    let _ = url.set_scheme(scheme);
+    Ok(url)
+}
+
+/// Url of the /settings/regions endpoint of a server, `url` is the ws url used to connect
+pub fn regions_url(url: &str) -> SignalResult<url::Url> {
+    let url = url::Url::parse(url)?;
+    let scheme = match url.scheme() {
+        "ws" | "http" => "http",
+        _ => "https",
+    };
+    let host = url.host_str().ok_or(url::ParseError::EmptyHost)?;
+    let port = url
+        .port()
+        .map(|port| format!(":{}", port))
+        .unwrap_or_default();
+
+    Ok(url::Url::parse(&format!(
+        "{}://{}{}/settings/regions",
+        scheme, host, port
+    ))?)
+}
+
+/// Fetch the regions able to host the room, sorted from the closest to the farthest
+pub async fn fetch_regions(
+    url: &str,
+    token: &str,
+    timeout: Duration,
+) -> SignalResult<Vec<RegionInfo>> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|err| SignalError::RegionError(err.to_string()))?;
+
+    let res = client
+        .get(regions_url(url)?)
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|err| SignalError::RegionError(err.to_string()))?;
+
+    if !res.status().is_success() {
+        return Err(SignalError::RegionError(format!(
+            "unexpected status: {}",
+            res.status()
+        )));
+    }
+
+    let mut settings: RegionSettings = res
+        .json()
+        .await
+        .map_err(|err| SignalError::RegionError(err.to_string()))?;
+
+    settings.regions.sort_by_key(|region| region.distance);
+    Ok(settings.regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fetch_regions, is_cloud_url, regions_url, ws_url, RegionInfo};
+    use crate::signal_client::{SignalClient, SignalError, SignalOptions};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const TEST_TOKEN: &str = "mytoken";
+    const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Serve a single HTTP request and return the raw request received
+    async fn serve_once(listener: TcpListener, status: &str, body: &str) -> String {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).to_string()
+    }
+
+    #[test]
+    fn test_regions_url() {
+        assert_eq!(
+            regions_url("wss://test.livekit.cloud?access_token=abc")
+                .unwrap()
+                .as_str(),
+            "https://test.livekit.cloud/settings/regions"
+        );
+        assert_eq!(
+            regions_url("ws://localhost:7880").unwrap().as_str(),
+            "http://localhost:7880/settings/regions"
+        );
+        assert_eq!(
+            regions_url("https://test.livekit.cloud/rtc")
+                .unwrap()
+                .as_str(),
+            "https://test.livekit.cloud/settings/regions"
+        );
+    }
+
+    #[test]
+    fn test_ws_url() {
+        assert_eq!(
+            ws_url("https://test.livekit.cloud").unwrap().as_str(),
+            "wss://test.livekit.cloud/"
+        );
+        assert_eq!(
+            ws_url("http://localhost:7880").unwrap().as_str(),
+            "ws://localhost:7880/"
+        );
+        assert_eq!(
+            ws_url("wss://test.livekit.cloud/").unwrap().as_str(),
+            "wss://test.livekit.cloud/"
+        );
+        assert!(ws_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_is_cloud_url() {
+        assert!(is_cloud_url(&"wss://test.livekit.cloud".parse().unwrap()));
+        assert!(is_cloud_url(
+            &"wss://test.staging.livekit.run".parse().unwrap()
+        ));
+        assert!(!is_cloud_url(&"ws://localhost:7880".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_regions() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let body = r#"{"regions":[
+            {"region":"eu","url":"wss://eu.livekit.cloud","distance":"900"},
+            {"region":"us","url":"wss://us.livekit.cloud","distance":"100"}
+        ]}"#;
+        let server = tokio::spawn(serve_once(listener, "200 OK", body));
+
+        let regions = fetch_regions(&url, TEST_TOKEN, TEST_TIMEOUT).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert!(request.starts_with("GET /settings/regions "));
+        assert!(request
+            .to_lowercase()
+            .contains(&format!("authorization: bearer {}", TEST_TOKEN)));
+        assert_eq!(
+            regions,
+            vec![
+                RegionInfo {
+                    region: "us".to_owned(),
+                    url: "wss://us.livekit.cloud".to_owned(),
+                    distance: 100,
+                },
+                RegionInfo {
+                    region: "eu".to_owned(),
+                    url: "wss://eu.livekit.cloud".to_owned(),
+                    distance: 900,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_regions_unauthorized() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve_once(listener, "401 Unauthorized", "{}"));
+
+        assert!(fetch_regions(&url, TEST_TOKEN, TEST_TIMEOUT).await.is_err());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_region_fallback() {
+        // Both regions are unavailable, they must be tried in order after the regions fetch
+        let settings_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let near_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let far_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let url = format!("ws://{}", settings_listener.local_addr().unwrap());
+        let body = format!(
+            r#"{{"regions":[
+                {{"region":"far","url":"ws://{}","distance":"900"}},
+                {{"region":"near","url":"ws://{}","distance":"100"}}
+            ]}}"#,
+            far_listener.local_addr().unwrap(),
+            near_listener.local_addr().unwrap()
+        );
+
+        let settings =
+            tokio::spawn(async move { serve_once(settings_listener, "200 OK", &body).await });
+        let near = tokio::spawn(serve_once(near_listener, "503 Service Unavailable", ""));
+        let far = tokio::spawn(serve_once(far_listener, "503 Service Unavailable", ""));
+
+        let res = SignalClient::connect_regions(
+            &url,
+            TEST_TOKEN,
+            SignalOptions::default(),
+            SignalError::ConnectTimeout,
+        )
+        .await;
+
+        assert!(matches!(res, Err(SignalError::WsError(_))));
+        assert!(settings
+            .await
+            .unwrap()
+            .starts_with("GET /settings/regions "));
+        assert!(near.await.unwrap().starts_with("GET /rtc?"));
+        assert!(far.await.unwrap().starts_with("GET /rtc?"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_regions_timeout() {
+        // The server accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let res = fetch_regions(&url, TEST_TOKEN, Duration::from_millis(200)).await;
+        assert!(matches!(res, Err(SignalError::RegionError(_))));
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_region_fallback_http_urls() {
+        // LiveKit Cloud returns https urls, they must be converted to websocket urls
+        let settings_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let region_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let url = format!("ws://{}", settings_listener.local_addr().unwrap());
+        let body = format!(
+            r#"{{"regions":[{{"region":"near","url":"http://{}/","distance":"100"}}]}}"#,
+            region_listener.local_addr().unwrap()
+        );
+
+        let settings =
+            tokio::spawn(async move { serve_once(settings_listener, "200 OK", &body).await });
+        let region = tokio::spawn(serve_once(region_listener, "503 Service Unavailable", ""));
+
+        let res = SignalClient::connect_regions(
+            &url,
+            TEST_TOKEN,
+            SignalOptions::default(),
+            SignalError::ConnectTimeout,
+        )
+        .await;
+
+        // The handshake reached the region instead of failing on the scheme
+        assert!(matches!(res, Err(SignalError::WsError(_))));
+        settings.await.unwrap();
+        assert!(region.await.unwrap().starts_with("GET /rtc?"));
+    }
+}
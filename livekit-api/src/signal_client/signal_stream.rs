@@ -1,8 +1,10 @@
-use crate::signal_client::{SignalEmitter, SignalEvent, SignalResult};
+use crate::signal_client::{SignalEmitter, SignalError, SignalEvent, SignalResult};
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use livekit_protocol as proto;
 use prost::Message as ProstMessage;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
@@ -42,8 +44,12 @@ impl SignalStream {
     /// Return SignalError if the connections failed
     ///
     /// SignalStream will never try to reconnect if the connection has been
-    /// closed.
-    pub async fn connect(url: url::Url, emitter: SignalEmitter) -> SignalResult<Self> {
+    /// closed, `reconnecting` is set when the connection is lost (and not closed by us)
+    pub async fn connect(
+        url: url::Url,
+        emitter: SignalEmitter,
+        reconnecting: Arc<AtomicBool>,
+    ) -> SignalResult<Self> {
         log::info!("connecting to SignalClient: {}", url);
 
         let (ws_stream, _) = connect_async(url).await?;
@@ -52,7 +58,12 @@ impl SignalStream {
         let (ws_writer, ws_reader) = ws_stream.split();
         let (internal_tx, internal_rx) = mpsc::channel::<InternalMessage>(8);
 
-        let write_handle = tokio::spawn(Self::write_task(internal_rx, ws_writer, emitter.clone()));
+        let write_handle = tokio::spawn(Self::write_task(
+            internal_rx,
+            ws_writer,
+            emitter.clone(),
+            reconnecting,
+        ));
         let read_handle = tokio::spawn(Self::read_task(internal_tx.clone(), ws_reader, emitter));
 
         Ok(Self {
@@ -87,8 +98,13 @@ impl SignalStream {
             signal,
             response_chn: send,
         };
-        let _ = self.internal_tx.send(msg).await;
-        recv.await.expect("channel closed")
+        self.internal_tx
+            .send(msg)
+            .await
+            .map_err(|_| SignalError::SendError("the stream is closed".to_string()))?;
+
+        recv.await
+            .map_err(|_| SignalError::SendError("the stream is closed".to_string()))?
     }
 
     /// This task is used to send messages to the websocket
@@ -97,7 +113,9 @@ impl SignalStream {
         mut internal_rx: mpsc::Receiver<InternalMessage>,
         mut ws_writer: SplitSink<WebSocket, Message>,
         emitter: SignalEmitter,
+        reconnecting: Arc<AtomicBool>,
     ) {
+        let mut lost = true;
         while let Some(msg) = internal_rx.recv().await {
             match msg {
                 InternalMessage::Signal {
@@ -130,6 +148,7 @@ impl SignalStream {
                     if let Some(close_frame) = close_frame {
                         let _ = ws_writer.send(Message::Close(Some(close_frame))).await;
                         let _ = ws_writer.flush().await;
+                        lost = false; // Closed by the client
                     }
                     break;
                 }
@@ -137,6 +156,10 @@ impl SignalStream {
         }
 
         let _ = ws_writer.close().await;
+        if lost {
+            // Queue the requests until the SignalClient is resumed
+            reconnecting.store(true, Ordering::Release);
+        }
         let _ = emitter.send(SignalEvent::Close).await;
     }
 
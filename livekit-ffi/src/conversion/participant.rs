@@ -10,6 +10,7 @@ macro_rules! impl_participant_into {
                     sid: p.sid().to_string(),
                     identity: p.identity().to_string(),
                     metadata: p.metadata(),
+                    attributes: p.attributes(),
                     publications: p.tracks().iter().map(|(_, p)| p.into()).collect(),
                 }
             }
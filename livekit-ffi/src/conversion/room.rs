@@ -1,14 +1,66 @@
 use crate::{proto, FfiHandleId};
 use livekit::options::{AudioEncoding, TrackPublishOptions, VideoEncoding};
 use livekit::prelude::*;
+use livekit::webrtc::prelude::{ContinualGatheringPolicy, IceServer, IceTransportsType};
+
+impl From<proto::IceTransportType> for IceTransportsType {
+    fn from(value: proto::IceTransportType) -> Self {
+        match value {
+            proto::IceTransportType::TransportRelay => Self::Relay,
+            proto::IceTransportType::TransportNohost => Self::NoHost,
+            proto::IceTransportType::TransportAll => Self::All,
+        }
+    }
+}
+
+impl From<proto::ContinualGatheringPolicy> for ContinualGatheringPolicy {
+    fn from(value: proto::ContinualGatheringPolicy) -> Self {
+        match value {
+            proto::ContinualGatheringPolicy::GatherOnce => Self::GatherOnce,
+            proto::ContinualGatheringPolicy::GatherContinually => Self::GatherContinually,
+        }
+    }
+}
+
+impl From<proto::IceServer> for IceServer {
+    fn from(value: proto::IceServer) -> Self {
+        Self {
+            urls: value.urls,
+            username: value.username,
+            password: value.password,
+        }
+    }
+}
 
 impl From<proto::RoomOptions> for RoomOptions {
     fn from(value: proto::RoomOptions) -> Self {
-        Self {
+        let mut options = Self {
             adaptive_stream: value.adaptive_stream,
             auto_subscribe: value.auto_subscribe,
             dynacast: value.dynacast,
+            ..Default::default()
+        };
+
+        if let Some(rtc_config) = value.rtc_config {
+            let rtc_options = &mut options.rtc_config;
+            if let Some(ice_transport_type) = rtc_config
+                .ice_transport_type
+                .and_then(proto::IceTransportType::from_i32)
+            {
+                rtc_options.ice_transport_type = ice_transport_type.into();
+            }
+
+            if let Some(continual_gathering_policy) = rtc_config
+                .continual_gathering_policy
+                .and_then(proto::ContinualGatheringPolicy::from_i32)
+            {
+                rtc_options.continual_gathering_policy = continual_gathering_policy.into();
+            }
+
+            rtc_options.ice_servers = rtc_config.ice_servers.into_iter().map(Into::into).collect();
         }
+
+        options
     }
 }
 
@@ -21,6 +73,22 @@ impl From<proto::DataPacketKind> for DataPacketKind {
     }
 }
 
+impl From<DisconnectReason> for proto::DisconnectReason {
+    fn from(value: DisconnectReason) -> Self {
+        match value {
+            DisconnectReason::Unknown => Self::ReasonUnknown,
+            DisconnectReason::ClientInitiated => Self::ReasonClientInitiated,
+            DisconnectReason::DuplicateIdentity => Self::ReasonDuplicateIdentity,
+            DisconnectReason::ServerShutdown => Self::ReasonServerShutdown,
+            DisconnectReason::ParticipantRemoved => Self::ReasonParticipantRemoved,
+            DisconnectReason::RoomDeleted => Self::ReasonRoomDeleted,
+            DisconnectReason::StateMismatch => Self::ReasonStateMismatch,
+            DisconnectReason::JoinFailure => Self::ReasonJoinFailure,
+            DisconnectReason::ReconnectExhausted => Self::ReasonReconnectExhausted,
+        }
+    }
+}
+
 impl proto::RoomInfo {
     pub fn from_room(handle_id: FfiHandleId, session: &Room) -> Self {
         Self {
@@ -13,6 +13,7 @@ struct DataPacket {
     data: Vec<u8>,
     kind: DataPacketKind,
     destination_sids: Vec<String>,
+    topic: Option<String>,
     async_id: FfiAsyncId,
 }
 
@@ -88,6 +89,7 @@ impl FfiRoom {
             data: data.to_vec(), // Avoid copy?
             kind: kind.into(),
             destination_sids,
+            topic: publish.topic,
             async_id,
         };
 
@@ -129,6 +131,7 @@ async fn data_task(
                     event.data,
                     event.kind,
                     event.destination_sids,
+                    event.topic,
                 ).await;
 
                 let cb = proto::PublishDataCallback {
@@ -214,6 +217,69 @@ async fn room_task(
                             track_sid: track.sid().to_string(),
                         },
                     )),
+                    RoomEvent::TrackMuted {
+                        participant,
+                        publication,
+                    } => Some(proto::room_event::Message::TrackMuted(
+                        proto::TrackMuted {
+                            participant_sid: participant.sid().to_string(),
+                            track_sid: publication.sid().to_string(),
+                        },
+                    )),
+                    RoomEvent::TrackUnmuted {
+                        participant,
+                        publication,
+                    } => Some(proto::room_event::Message::TrackUnmuted(
+                        proto::TrackUnmuted {
+                            participant_sid: participant.sid().to_string(),
+                            track_sid: publication.sid().to_string(),
+                        },
+                    )),
+                    RoomEvent::Disconnected { reason } => Some(proto::room_event::Message::Disconnected(
+                        proto::Disconnected {
+                            reason: proto::DisconnectReason::from(reason) as i32,
+                        },
+                    )),
+                    RoomEvent::ParticipantMetadataChanged {
+                        participant,
+                        old_metadata,
+                        metadata,
+                    } => Some(proto::room_event::Message::ParticipantMetadataChanged(
+                        proto::ParticipantMetadataChanged {
+                            participant_sid: participant.sid().to_string(),
+                            old_metadata,
+                            metadata,
+                        },
+                    )),
+                    RoomEvent::ParticipantNameChanged {
+                        participant,
+                        old_name,
+                        name,
+                    } => Some(proto::room_event::Message::ParticipantNameChanged(
+                        proto::ParticipantNameChanged {
+                            participant_sid: participant.sid().to_string(),
+                            old_name,
+                            name,
+                        },
+                    )),
+                    RoomEvent::ParticipantAttributesChanged {
+                        participant,
+                        changed_attributes,
+                    } => Some(proto::room_event::Message::ParticipantAttributesChanged(
+                        proto::ParticipantAttributesChanged {
+                            participant_sid: participant.sid().to_string(),
+                            changed_attributes,
+                        },
+                    )),
+                    RoomEvent::RoomMetadataChanged {
+                        old_metadata,
+                        metadata,
+                    } => Some(proto::room_event::Message::RoomMetadataChanged(
+                        proto::RoomMetadataChanged {
+                            old_metadata,
+                            metadata,
+                        },
+                    )),
                     _ => None
                 } {
                     // Send the event to the FfiClient
@@ -67,6 +67,15 @@ impl DataChannel {
         self.handle.label()
     }
 
+    pub fn id(&self) -> i32 {
+        self.handle.id()
+    }
+
+    /// Number of bytes queued to be sent
+    pub fn buffered_amount(&self) -> u64 {
+        self.handle.buffered_amount()
+    }
+
     pub fn state(&self) -> DataState {
         self.handle.state()
     }
@@ -42,6 +42,7 @@ pub mod rtp_receiver;
 pub mod rtp_sender;
 pub mod rtp_transceiver;
 pub mod session_description;
+pub mod stats;
 pub mod video_frame;
 pub mod video_source;
 pub mod video_stream;
@@ -77,6 +77,14 @@ impl DataChannel {
         self.sys_handle.label()
     }
 
+    pub fn id(&self) -> i32 {
+        self.sys_handle.id()
+    }
+
+    pub fn buffered_amount(&self) -> u64 {
+        self.sys_handle.buffered_amount()
+    }
+
     pub fn state(&self) -> DataState {
         self.sys_handle.state().into()
     }
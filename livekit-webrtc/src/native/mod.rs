@@ -19,6 +19,7 @@ pub mod video_stream;
 pub mod video_track;
 pub mod yuv_helper;
 
+use crate::stats::RtcStats;
 use crate::MediaType;
 use crate::{RtcError, RtcErrorType};
 use webrtc_sys::rtc_error as sys_err;
@@ -42,6 +43,22 @@ impl From<sys_err::ffi::RtcError> for RtcError {
     }
 }
 
+/// The stats reports are serialized to JSON by webrtc-sys
+pub(crate) fn stats_from_json(json: &str) -> Result<Vec<RtcStats>, RtcError> {
+    // Empty when the sender/receiver isn't attached to a PeerConnection
+    if json.is_empty() {
+        return Err(RtcError {
+            error_type: RtcErrorType::InvalidState,
+            message: "stats are unavailable without a peer connection".to_owned(),
+        });
+    }
+
+    serde_json::from_str(json).map_err(|err| RtcError {
+        error_type: RtcErrorType::Internal,
+        message: format!("failed to parse the stats report: {}", err),
+    })
+}
+
 impl From<MediaType> for sys_rtc::ffi::MediaType {
     fn from(value: MediaType) -> Self {
         match value {
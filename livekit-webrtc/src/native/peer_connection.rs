@@ -21,8 +21,9 @@ use crate::rtp_receiver::RtpReceiver;
 use crate::rtp_sender::RtpSender;
 use crate::rtp_transceiver::RtpTransceiver;
 use crate::rtp_transceiver::RtpTransceiverInit;
+use crate::stats::RtcStats;
 use crate::MediaType;
-use crate::{session_description::SessionDescription, RtcError};
+use crate::{session_description::SessionDescription, RtcError, RtcErrorType};
 use cxx::SharedPtr;
 use futures::channel::oneshot;
 use parking_lot::Mutex;
@@ -257,6 +258,25 @@ impl PeerConnection {
         rx.await.unwrap()
     }
 
+    pub async fn get_stats(&self) -> Result<Vec<RtcStats>, RtcError> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RtcStats>, RtcError>>();
+        let ctx = Box::new(sys_pc::AsyncContext(Box::new(tx)));
+
+        self.sys_handle.get_stats(ctx, |ctx, json| {
+            let tx = ctx
+                .0
+                .downcast::<oneshot::Sender<Result<Vec<RtcStats>, RtcError>>>()
+                .unwrap();
+
+            let _ = tx.send(super::stats_from_json(&json));
+        });
+
+        rx.await.map_err(|_| RtcError {
+            error_type: RtcErrorType::Internal,
+            message: "stats for PeerConnection cancelled".to_owned(),
+        })?
+    }
+
     pub fn create_data_channel(
         &self,
         label: &str,
@@ -33,6 +33,7 @@ impl From<sys_rp::ffi::RtpParameters> for RtpParameters {
                 .into_iter()
                 .map(Into::into)
                 .collect(),
+            encodings: value.encodings.into_iter().map(Into::into).collect(),
             rtcp: value.rtcp.into(),
         }
     }
@@ -156,7 +157,7 @@ impl From<RtpParameters> for sys_rp::ffi::RtpParameters {
                 .into_iter()
                 .map(Into::into)
                 .collect(),
-            encodings: Vec::new(),
+            encodings: value.encodings.into_iter().map(Into::into).collect(),
             rtcp: value.rtcp.into(),
             transaction_id: "".to_string(),
             mid: "".to_string(),
@@ -1,7 +1,10 @@
 use crate::imp::media_stream_track::new_media_stream_track;
 use crate::media_stream_track::MediaStreamTrack;
 use crate::rtp_parameters::RtpParameters;
+use crate::stats::RtcStats;
+use crate::{RtcError, RtcErrorType};
 use cxx::SharedPtr;
+use futures::channel::oneshot;
 use webrtc_sys::rtp_receiver as sys_rr;
 
 #[derive(Clone)]
@@ -22,4 +25,23 @@ impl RtpReceiver {
     pub fn parameters(&self) -> RtpParameters {
         self.sys_handle.get_parameters().into()
     }
+
+    pub async fn get_stats(&self) -> Result<Vec<RtcStats>, RtcError> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RtcStats>, RtcError>>();
+        let ctx = Box::new(sys_rr::ReceiverContext(Box::new(tx)));
+
+        self.sys_handle.get_stats(ctx, |ctx, json| {
+            let tx = ctx
+                .0
+                .downcast::<oneshot::Sender<Result<Vec<RtcStats>, RtcError>>>()
+                .unwrap();
+
+            let _ = tx.send(super::stats_from_json(&json));
+        });
+
+        rx.await.map_err(|_| RtcError {
+            error_type: RtcErrorType::Internal,
+            message: "stats for RtpReceiver cancelled".to_owned(),
+        })?
+    }
 }
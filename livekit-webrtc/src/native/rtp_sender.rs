@@ -1,7 +1,9 @@
 use super::media_stream_track::new_media_stream_track;
 use crate::media_stream_track::MediaStreamTrack;
+use crate::stats::RtcStats;
 use crate::{rtp_parameters::RtpParameters, RtcError, RtcErrorType};
 use cxx::SharedPtr;
+use futures::channel::oneshot;
 use webrtc_sys::rtc_error as sys_err;
 use webrtc_sys::rtp_sender as sys_rs;
 
@@ -38,9 +40,71 @@ impl RtpSender {
         self.sys_handle.get_parameters().into()
     }
 
+    /// Only the modifiable fields are applied on top of the current parameters.
+    /// `codecs` and `header_extensions` are read-only, they must be empty or unchanged.
+    /// Empty `encodings` keep the current ones, otherwise their number can't change
     pub fn set_parameters(&self, parameters: RtpParameters) -> Result<(), RtcError> {
+        let current = self.parameters();
+        let read_only_error = |field: &str| {
+            Err(RtcError {
+                error_type: RtcErrorType::InvalidState,
+                message: format!("the {} of a sender can't be changed", field),
+            })
+        };
+
+        if !parameters.codecs.is_empty() && parameters.codecs != current.codecs {
+            return read_only_error("codecs");
+        }
+
+        if !parameters.header_extensions.is_empty()
+            && parameters.header_extensions != current.header_extensions
+        {
+            return read_only_error("header extensions");
+        }
+
+        let mut native = self.sys_handle.get_parameters();
+        if !parameters.encodings.is_empty() && native.encodings.len() != parameters.encodings.len()
+        {
+            return read_only_error("number of encodings");
+        }
+
+        for (native_encoding, encoding) in native.encodings.iter_mut().zip(parameters.encodings) {
+            native_encoding.active = encoding.active;
+            native_encoding.has_max_bitrate_bps = encoding.max_bitrate.is_some();
+            native_encoding.max_bitrate_bps = encoding
+                .max_bitrate
+                .map_or(0, |bitrate| i32::try_from(bitrate).unwrap_or(i32::MAX));
+            native_encoding.has_max_framerate = encoding.max_framerate.is_some();
+            native_encoding.max_framerate = encoding.max_framerate.unwrap_or_default();
+            native_encoding.has_scale_resolution_down_by =
+                encoding.scale_resolution_down_by.is_some();
+            native_encoding.scale_resolution_down_by =
+                encoding.scale_resolution_down_by.unwrap_or_default();
+            native_encoding.network_priority = encoding.priority.into();
+        }
+        native.rtcp.reduced_size = parameters.rtcp.reduced_size;
+
         self.sys_handle
-            .set_parameters(parameters.into())
+            .set_parameters(native)
             .map_err(|e| unsafe { sys_err::ffi::RtcError::from(e.what()).into() })
     }
+
+    pub async fn get_stats(&self) -> Result<Vec<RtcStats>, RtcError> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RtcStats>, RtcError>>();
+        let ctx = Box::new(sys_rs::SenderContext(Box::new(tx)));
+
+        self.sys_handle.get_stats(ctx, |ctx, json| {
+            let tx = ctx
+                .0
+                .downcast::<oneshot::Sender<Result<Vec<RtcStats>, RtcError>>>()
+                .unwrap();
+
+            let _ = tx.send(super::stats_from_json(&json));
+        });
+
+        rx.await.map_err(|_| RtcError {
+            error_type: RtcErrorType::Internal,
+            message: "stats for RtpSender cancelled".to_owned(),
+        })?
+    }
 }
@@ -9,6 +9,7 @@ use crate::rtp_receiver::RtpReceiver;
 use crate::rtp_sender::RtpSender;
 use crate::rtp_transceiver::{RtpTransceiver, RtpTransceiverInit};
 use crate::session_description::SessionDescription;
+use crate::stats::RtcStats;
 use crate::{MediaType, RtcError};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -119,6 +120,10 @@ impl PeerConnection {
         self.handle.add_ice_candidate(candidate).await
     }
 
+    pub async fn get_stats(&self) -> Result<Vec<RtcStats>, RtcError> {
+        self.handle.get_stats().await
+    }
+
     pub fn create_data_channel(
         &self,
         label: &str,
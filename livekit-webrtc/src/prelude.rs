@@ -19,6 +19,7 @@ pub use crate::rtp_receiver::RtpReceiver;
 pub use crate::rtp_sender::RtpSender;
 pub use crate::rtp_transceiver::{RtpTransceiver, RtpTransceiverDirection, RtpTransceiverInit};
 pub use crate::session_description::{SdpType, SessionDescription};
+pub use crate::stats::RtcStats;
 pub use crate::video_frame::{
     BoxVideoFrame, I010Buffer, I420ABuffer, I420Buffer, I422Buffer, I444Buffer, NV12Buffer,
     VideoFormatType, VideoFrame, VideoFrameBuffer, VideoFrameBufferType, VideoRotation,
@@ -8,7 +8,7 @@ pub enum Priority {
     High,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RtpHeaderExtensionParameters {
     pub uri: String,
     pub id: i32,
@@ -19,10 +19,11 @@ pub struct RtpHeaderExtensionParameters {
 pub struct RtpParameters {
     pub codecs: Vec<RtpCodecParameters>,
     pub header_extensions: Vec<RtpHeaderExtensionParameters>,
+    pub encodings: Vec<RtpEncodingParameters>,
     pub rtcp: RtcpParameters,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RtpCodecParameters {
     pub payload_type: u8,
     pub mime_type: String, // read-only
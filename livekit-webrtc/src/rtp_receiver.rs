@@ -2,7 +2,7 @@ use std::fmt::Debug;
 
 use crate::{
     imp::rtp_receiver as imp_rr, media_stream_track::MediaStreamTrack,
-    rtp_parameters::RtpParameters,
+    rtp_parameters::RtpParameters, stats::RtcStats, RtcError,
 };
 
 #[derive(Clone)]
@@ -18,6 +18,10 @@ impl RtpReceiver {
     pub fn parameters(&self) -> RtpParameters {
         self.handle.parameters()
     }
+
+    pub async fn get_stats(&self) -> Result<Vec<RtcStats>, RtcError> {
+        self.handle.get_stats().await
+    }
 }
 
 impl Debug for RtpReceiver {
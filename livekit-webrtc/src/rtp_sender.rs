@@ -2,7 +2,7 @@ use std::fmt::Debug;
 
 use crate::{
     imp::rtp_sender as imp_rs, media_stream_track::MediaStreamTrack, rtp_parameters::RtpParameters,
-    RtcError,
+    stats::RtcStats, RtcError,
 };
 
 #[derive(Clone)]
@@ -26,6 +26,10 @@ impl RtpSender {
     pub fn set_parameters(&self, parameters: RtpParameters) -> Result<(), RtcError> {
         self.handle.set_parameters(parameters)
     }
+
+    pub async fn get_stats(&self) -> Result<Vec<RtcStats>, RtcError> {
+        self.handle.get_stats().await
+    }
 }
 
 impl Debug for RtpSender {
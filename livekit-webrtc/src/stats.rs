@@ -0,0 +1,199 @@
+use serde::Deserialize;
+
+// https://www.w3.org/TR/webrtc-stats/
+// Only the most useful dictionaries are typed, the others are ignored
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum RtcStats {
+    Codec(CodecStats),
+    InboundRtp(InboundRtpStats),
+    OutboundRtp(OutboundRtpStats),
+    MediaSource(MediaSourceStats),
+    CandidatePair(CandidatePairStats),
+    Transport(TransportStats),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CodecStats {
+    pub id: String,
+    pub timestamp: f64,
+    pub payload_type: u32,
+    pub transport_id: String,
+    pub mime_type: String,
+    pub clock_rate: u32,
+    pub channels: u32,
+    pub sdp_fmtp_line: String,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InboundRtpStats {
+    pub id: String,
+    pub timestamp: f64,
+    pub ssrc: u32,
+    pub kind: String,
+    pub transport_id: String,
+    pub codec_id: String,
+    pub track_identifier: String,
+    pub mid: String,
+    pub packets_received: u64,
+    pub packets_lost: i64,
+    pub jitter: f64,
+    pub bytes_received: u64,
+    pub header_bytes_received: u64,
+    pub last_packet_received_timestamp: f64,
+    pub jitter_buffer_delay: f64,
+    pub jitter_buffer_emitted_count: u64,
+    pub nack_count: u32,
+    pub pli_count: u32,
+    pub fir_count: u32,
+
+    // Video
+    pub frames_decoded: u32,
+    pub key_frames_decoded: u32,
+    pub frames_dropped: u32,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub frames_per_second: f64,
+    pub total_decode_time: f64,
+
+    // Audio
+    pub audio_level: f64,
+    pub total_audio_energy: f64,
+    pub total_samples_received: u64,
+    pub concealed_samples: u64,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OutboundRtpStats {
+    pub id: String,
+    pub timestamp: f64,
+    pub ssrc: u32,
+    pub kind: String,
+    pub transport_id: String,
+    pub codec_id: String,
+    pub media_source_id: String,
+    pub mid: String,
+    pub rid: String,
+    pub active: bool,
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    pub header_bytes_sent: u64,
+    pub retransmitted_packets_sent: u64,
+    pub retransmitted_bytes_sent: u64,
+    pub target_bitrate: f64,
+    pub nack_count: u32,
+    pub pli_count: u32,
+    pub fir_count: u32,
+
+    // Video
+    pub frames_encoded: u32,
+    pub key_frames_encoded: u32,
+    pub frames_sent: u32,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub frames_per_second: f64,
+    pub total_encode_time: f64,
+    pub quality_limitation_reason: String,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MediaSourceStats {
+    pub id: String,
+    pub timestamp: f64,
+    pub kind: String,
+    pub track_identifier: String,
+
+    // Audio
+    pub audio_level: f64,
+    pub total_audio_energy: f64,
+    pub total_samples_duration: f64,
+
+    // Video
+    pub width: u32,
+    pub height: u32,
+    pub frames: u32,
+    pub frames_per_second: f64,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CandidatePairStats {
+    pub id: String,
+    pub timestamp: f64,
+    pub transport_id: String,
+    pub local_candidate_id: String,
+    pub remote_candidate_id: String,
+    pub state: String,
+    pub nominated: bool,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub total_round_trip_time: f64,
+    pub current_round_trip_time: f64,
+    pub available_outgoing_bitrate: f64,
+    pub available_incoming_bitrate: f64,
+    pub requests_received: u64,
+    pub requests_sent: u64,
+    pub responses_received: u64,
+    pub responses_sent: u64,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransportStats {
+    pub id: String,
+    pub timestamp: f64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub dtls_state: String,
+    pub ice_state: String,
+    pub selected_candidate_pair_id: String,
+    pub selected_candidate_pair_changes: u32,
+    pub local_certificate_id: String,
+    pub remote_certificate_id: String,
+    pub tls_version: String,
+    pub dtls_cipher: String,
+    pub srtp_cipher: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_report() {
+        let json = r#"[
+            {"type":"outbound-rtp","id":"OT01V1","timestamp":1690000000000.0,"ssrc":1234,"kind":"video","packetsSent":120,"bytesSent":98000,"frameWidth":1280,"frameHeight":720,"framesPerSecond":30,"qualityLimitationReason":"none"},
+            {"type":"candidate-pair","id":"CP01","timestamp":1690000000000.0,"state":"succeeded","nominated":true,"currentRoundTripTime":0.042},
+            {"type":"certificate","id":"CF01","timestamp":1690000000000.0,"fingerprint":"AB:CD"}
+        ]"#;
+
+        let stats: Vec<RtcStats> = serde_json::from_str(json).unwrap();
+        assert_eq!(stats.len(), 3);
+
+        let RtcStats::OutboundRtp(outbound) = &stats[0] else {
+            panic!("expected outbound-rtp stats");
+        };
+        assert_eq!(outbound.ssrc, 1234);
+        assert_eq!(outbound.frame_width, 1280);
+        assert_eq!(outbound.quality_limitation_reason, "none");
+
+        let RtcStats::CandidatePair(pair) = &stats[1] else {
+            panic!("expected candidate-pair stats");
+        };
+        assert!(pair.nominated);
+        assert_eq!(pair.current_round_trip_time, 0.042);
+
+        assert!(matches!(stats[2], RtcStats::Unknown));
+    }
+}
@@ -1,7 +1,8 @@
 pub use crate::participant::{LocalParticipant, Participant, RemoteParticipant};
 
 pub use crate::{
-    ConnectionState, DataPacketKind, Room, RoomError, RoomEvent, RoomOptions, RoomResult,
+    ConnectionState, DataPacketKind, DisconnectReason, ReceivedData, Room, RoomError, RoomEvent,
+    RoomOptions, RoomResult,
 };
 
 pub use crate::publication::{LocalTrackPublication, RemoteTrackPublication, TrackPublication};
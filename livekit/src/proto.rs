@@ -12,6 +12,37 @@ impl From<ConnectionQuality> for participant::ConnectionQuality {
     }
 }
 
+impl From<participant_info::Kind> for participant::ParticipantKind {
+    fn from(kind: participant_info::Kind) -> Self {
+        match kind {
+            participant_info::Kind::Standard => Self::Standard,
+            participant_info::Kind::Ingress => Self::Ingress,
+            participant_info::Kind::Egress => Self::Egress,
+            participant_info::Kind::Sip => Self::Sip,
+            participant_info::Kind::Agent => Self::Agent,
+        }
+    }
+}
+
+impl From<ParticipantPermission> for participant::ParticipantPermissions {
+    fn from(permission: ParticipantPermission) -> Self {
+        Self {
+            can_subscribe: permission.can_subscribe,
+            can_publish: permission.can_publish,
+            can_publish_data: permission.can_publish_data,
+            can_publish_sources: permission
+                .can_publish_sources
+                .into_iter()
+                .filter_map(TrackSource::from_i32)
+                .map(Into::into)
+                .collect(),
+            can_update_metadata: permission.can_update_metadata,
+            hidden: permission.hidden,
+            recorder: permission.recorder,
+        }
+    }
+}
+
 impl TryFrom<TrackType> for track::TrackKind {
     type Error = &'static str;
 
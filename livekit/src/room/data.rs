@@ -0,0 +1,364 @@
+use super::{data_stream, rpc};
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Topic of the packets containing a chunk of a payload too large to be sent in one packet.
+/// The topic of the payload itself is appended after a `/`
+const CHUNK_TOPIC: &str = "lk.chunk";
+
+/// Topics used by the SDK (chunks, RPC, streams), they can't be used with
+/// [LocalParticipant::publish_data]. The `lk.chunk/<topic>` topics are reserved too
+pub const RESERVED_TOPICS: [&str; 3] = [CHUNK_TOPIC, rpc::RPC_TOPIC, data_stream::STREAM_TOPIC];
+
+/// Max payload size of a single packet, bigger payloads are chunked.
+/// This stays below the SCTP message size limit of every WebRTC implementation
+pub(crate) const MAX_CHUNK_SIZE: usize = 15_000;
+
+/// Largest payload accepted by publish_data, bigger payloads must use a byte stream
+pub const MAX_DATA_PAYLOAD_SIZE: usize = 16 * 1024 * 1024;
+
+/// Reject messages announcing more chunks than needed by the largest payload
+const MAX_CHUNK_COUNT: u32 = ((MAX_DATA_PAYLOAD_SIZE + MAX_CHUNK_SIZE - 1) / MAX_CHUNK_SIZE) as u32;
+
+/// Incomplete messages a single participant can have at the same time
+const MAX_PENDING_MESSAGES_PER_SENDER: usize = 8;
+
+/// Bytes buffered for all the incomplete messages
+const MAX_PENDING_BYTES: usize = 4 * MAX_DATA_PAYLOAD_SIZE;
+
+/// Drop the messages still incomplete after this timeout (e.g. the sender disconnected)
+const INCOMPLETE_MESSAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// message_id (u64) + index (u32) + count (u32), big endian
+const CHUNK_HEADER_SIZE: usize = 16;
+
+pub(crate) fn is_reserved_topic(topic: &str) -> bool {
+    RESERVED_TOPICS.contains(&topic) || parse_chunk_topic(topic).is_some()
+}
+
+/// Topic used to send the chunks of a payload published on `topic`
+pub(crate) fn chunk_topic(topic: Option<&str>) -> String {
+    match topic {
+        Some(topic) => format!("{}/{}", CHUNK_TOPIC, topic),
+        None => CHUNK_TOPIC.to_owned(),
+    }
+}
+
+/// Returns the topic of the chunked payload if `topic` is a chunk topic
+/// (`lk.chunk` or `lk.chunk/<topic>`)
+pub(crate) fn parse_chunk_topic(topic: &str) -> Option<Option<String>> {
+    if topic == CHUNK_TOPIC {
+        return Some(None);
+    }
+
+    topic
+        .strip_prefix(CHUNK_TOPIC)?
+        .strip_prefix('/')
+        .map(|topic| Some(topic.to_owned()))
+}
+
+struct ChunkHeader {
+    message_id: u64,
+    index: u32,
+    count: u32,
+}
+
+/// Split a payload into chunks, each chunk is prefixed with a ChunkHeader
+pub(crate) fn encode_chunks(message_id: u64, payload: &[u8]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let count = ((payload.len() + MAX_CHUNK_SIZE - 1) / MAX_CHUNK_SIZE) as u32;
+    payload
+        .chunks(MAX_CHUNK_SIZE)
+        .enumerate()
+        .map(move |(index, data)| {
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER_SIZE + data.len());
+            chunk.extend_from_slice(&message_id.to_be_bytes());
+            chunk.extend_from_slice(&(index as u32).to_be_bytes());
+            chunk.extend_from_slice(&count.to_be_bytes());
+            chunk.extend_from_slice(data);
+            chunk
+        })
+}
+
+fn decode_chunk(chunk: &[u8]) -> Option<(ChunkHeader, &[u8])> {
+    if chunk.len() < CHUNK_HEADER_SIZE {
+        return None;
+    }
+
+    let (header, data) = chunk.split_at(CHUNK_HEADER_SIZE);
+    let header = ChunkHeader {
+        message_id: u64::from_be_bytes(header[0..8].try_into().unwrap()),
+        index: u32::from_be_bytes(header[8..12].try_into().unwrap()),
+        count: u32::from_be_bytes(header[12..16].try_into().unwrap()),
+    };
+
+    if header.count == 0
+        || header.count > MAX_CHUNK_COUNT
+        || header.index >= header.count
+        || data.len() > MAX_CHUNK_SIZE
+    {
+        return None;
+    }
+
+    Some((header, data))
+}
+
+struct PendingMessage {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: u32,
+    size: usize,
+    started_at: Instant,
+}
+
+/// Reassemble the chunked payloads received from the remote participants
+#[derive(Default)]
+pub(crate) struct ChunkAssembler {
+    messages: HashMap<(ParticipantSid, u64), PendingMessage>,
+    pending_bytes: usize,
+}
+
+impl ChunkAssembler {
+    /// Returns the whole payload once every chunk of the message has been received
+    pub fn push(&mut self, sender: &ParticipantSid, chunk: &[u8]) -> Option<Vec<u8>> {
+        self.remove_expired();
+
+        let Some((header, data)) = decode_chunk(chunk) else {
+            log::warn!("received an invalid data chunk from {}", sender);
+            return None;
+        };
+
+        if self.pending_bytes + data.len() > MAX_PENDING_BYTES {
+            log::warn!(
+                "too many pending data chunks, dropping a chunk from {}",
+                sender
+            );
+            return None;
+        }
+
+        let key = (sender.clone(), header.message_id);
+        if !self.messages.contains_key(&key) {
+            let sender_messages = self
+                .messages
+                .keys()
+                .filter(|(sid, _)| sid == sender)
+                .count();
+            if sender_messages >= MAX_PENDING_MESSAGES_PER_SENDER {
+                log::warn!("too many incomplete data messages from {}", sender);
+                return None;
+            }
+
+            self.messages.insert(
+                key.clone(),
+                PendingMessage {
+                    chunks: vec![None; header.count as usize],
+                    received: 0,
+                    size: 0,
+                    started_at: Instant::now(),
+                },
+            );
+        }
+
+        let message = self.messages.get_mut(&key)?;
+        if message.chunks.len() != header.count as usize {
+            log::warn!(
+                "received a data chunk with a mismatching count from {}",
+                sender
+            );
+            return None;
+        }
+
+        let slot = &mut message.chunks[header.index as usize];
+        if slot.is_none() {
+            *slot = Some(data.to_vec());
+            message.received += 1;
+            message.size += data.len();
+            self.pending_bytes += data.len();
+        }
+
+        if message.received as usize != message.chunks.len() {
+            return None;
+        }
+
+        let message = self.remove_message(&key)?;
+        Some(message.chunks.into_iter().flatten().flatten().collect())
+    }
+
+    /// Forget the incomplete messages of a participant (e.g. on disconnection)
+    pub fn remove_participant(&mut self, sid: &ParticipantSid) {
+        let keys: Vec<_> = self
+            .messages
+            .keys()
+            .filter(|(sender, _)| sender == sid)
+            .cloned()
+            .collect();
+
+        for key in keys {
+            self.remove_message(&key);
+        }
+    }
+
+    fn remove_expired(&mut self) {
+        let keys: Vec<_> = self
+            .messages
+            .iter()
+            .filter(|(_, message)| message.started_at.elapsed() >= INCOMPLETE_MESSAGE_TIMEOUT)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in keys {
+            self.remove_message(&key);
+        }
+    }
+
+    fn remove_message(&mut self, key: &(ParticipantSid, u64)) -> Option<PendingMessage> {
+        let message = self.messages.remove(key)?;
+        self.pending_bytes -= message.size;
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sid(sid: &str) -> ParticipantSid {
+        sid.to_owned().into()
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let payload = payload(MAX_CHUNK_SIZE * 2 + 10);
+        let chunks: Vec<_> = encode_chunks(1, &payload).collect();
+        assert_eq!(chunks.len(), 3);
+
+        let mut assembler = ChunkAssembler::default();
+        let sender = sid("PA_1");
+        assert_eq!(assembler.push(&sender, &chunks[0]), None);
+        assert_eq!(assembler.push(&sender, &chunks[1]), None);
+        assert_eq!(assembler.push(&sender, &chunks[2]), Some(payload));
+        assert_eq!(assembler.pending_bytes, 0);
+        assert!(assembler.messages.is_empty());
+    }
+
+    #[test]
+    fn test_out_of_order_and_duplicate_chunks() {
+        let payload = payload(MAX_CHUNK_SIZE * 2 + 10);
+        let chunks: Vec<_> = encode_chunks(1, &payload).collect();
+
+        let mut assembler = ChunkAssembler::default();
+        let sender = sid("PA_1");
+        assert_eq!(assembler.push(&sender, &chunks[2]), None);
+        assert_eq!(assembler.push(&sender, &chunks[2]), None);
+        assert_eq!(assembler.push(&sender, &chunks[0]), None);
+        assert_eq!(assembler.push(&sender, &chunks[0]), None);
+        assert_eq!(assembler.push(&sender, &chunks[1]), Some(payload));
+        assert_eq!(assembler.pending_bytes, 0);
+    }
+
+    #[test]
+    fn test_count_mismatch() {
+        let first: Vec<_> = encode_chunks(1, &payload(MAX_CHUNK_SIZE * 2)).collect();
+        let second: Vec<_> = encode_chunks(1, &payload(MAX_CHUNK_SIZE * 3)).collect();
+
+        let mut assembler = ChunkAssembler::default();
+        let sender = sid("PA_1");
+        assert_eq!(assembler.push(&sender, &first[0]), None);
+        assert_eq!(assembler.push(&sender, &second[1]), None);
+        assert_eq!(assembler.push(&sender, &second[2]), None);
+        assert_eq!(assembler.messages.values().next().unwrap().received, 1);
+    }
+
+    #[test]
+    fn test_invalid_chunks() {
+        let mut chunk = encode_chunks(1, &payload(10)).next().unwrap();
+        assert!(decode_chunk(&chunk[..CHUNK_HEADER_SIZE - 1]).is_none());
+
+        // index >= count
+        chunk[8..12].copy_from_slice(&1u32.to_be_bytes());
+        assert!(decode_chunk(&chunk).is_none());
+
+        // count above the largest accepted payload
+        chunk[8..12].copy_from_slice(&0u32.to_be_bytes());
+        chunk[12..16].copy_from_slice(&(MAX_CHUNK_COUNT + 1).to_be_bytes());
+        assert!(decode_chunk(&chunk).is_none());
+    }
+
+    #[test]
+    fn test_pending_messages_limit() {
+        let mut assembler = ChunkAssembler::default();
+        let sender = sid("PA_1");
+        for message_id in 0..MAX_PENDING_MESSAGES_PER_SENDER as u64 + 1 {
+            let chunk = encode_chunks(message_id, &payload(MAX_CHUNK_SIZE * 2))
+                .next()
+                .unwrap();
+            assert_eq!(assembler.push(&sender, &chunk), None);
+        }
+        assert_eq!(assembler.messages.len(), MAX_PENDING_MESSAGES_PER_SENDER);
+
+        // Other participants aren't affected
+        let chunk = encode_chunks(0, &payload(MAX_CHUNK_SIZE * 2))
+            .next()
+            .unwrap();
+        assembler.push(&sid("PA_2"), &chunk);
+        assert_eq!(
+            assembler.messages.len(),
+            MAX_PENDING_MESSAGES_PER_SENDER + 1
+        );
+
+        assembler.remove_participant(&sender);
+        assert_eq!(assembler.messages.len(), 1);
+        assert_eq!(assembler.pending_bytes, MAX_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_timeout_eviction() {
+        let chunks: Vec<_> = encode_chunks(1, &payload(MAX_CHUNK_SIZE * 2)).collect();
+
+        let mut assembler = ChunkAssembler::default();
+        let sender = sid("PA_1");
+        assert_eq!(assembler.push(&sender, &chunks[0]), None);
+
+        let Some(started_at) = Instant::now().checked_sub(INCOMPLETE_MESSAGE_TIMEOUT) else {
+            return; // The monotonic clock started less than a timeout ago
+        };
+        assembler.messages.values_mut().next().unwrap().started_at = started_at;
+
+        // The expired message is dropped, the last chunk starts a new one
+        assert_eq!(assembler.push(&sender, &chunks[1]), None);
+        assert_eq!(assembler.messages.values().next().unwrap().received, 1);
+        assert_eq!(assembler.pending_bytes, MAX_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_parse_chunk_topic() {
+        assert_eq!(parse_chunk_topic("lk.chunk"), Some(None));
+        assert_eq!(
+            parse_chunk_topic("lk.chunk/chat"),
+            Some(Some("chat".to_owned()))
+        );
+        assert_eq!(parse_chunk_topic(&chunk_topic(None)), Some(None));
+        assert_eq!(
+            parse_chunk_topic(&chunk_topic(Some("lk.chunk/chat"))),
+            Some(Some("lk.chunk/chat".to_owned()))
+        );
+        assert_eq!(parse_chunk_topic("lk.chunky"), None);
+        assert_eq!(parse_chunk_topic("chat"), None);
+    }
+
+    #[test]
+    fn test_reserved_topics() {
+        assert!(is_reserved_topic("lk.chunk"));
+        assert!(is_reserved_topic("lk.chunk/chat"));
+        assert!(is_reserved_topic("lk.rpc"));
+        assert!(is_reserved_topic("lk.stream"));
+
+        // Only the topics used by the SDK are reserved
+        assert!(!is_reserved_topic("lk.chunky"));
+        assert!(!is_reserved_topic("lk.chat"));
+        assert!(!is_reserved_topic("chat"));
+    }
+}
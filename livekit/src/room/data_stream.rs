@@ -0,0 +1,725 @@
+use crate::prelude::*;
+use crate::room::data;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Topic of the packets used by the text and byte streams
+pub(crate) const STREAM_TOPIC: &str = "lk.stream";
+
+// Packet types, the first byte of every packet sent on STREAM_TOPIC
+const CONTROL_PACKET: u8 = 0; // json StreamControl
+const CHUNK_PACKET: u8 = 1; // stream_id length (u8) + stream_id + index (u64 BE) + content
+
+/// Length of the stream ids, they are always generated by the writers
+const STREAM_ID_LEN: usize = 32;
+
+/// Max size of the content of a chunk, leaves room for the chunk header
+const STREAM_CHUNK_SIZE: usize = data::MAX_CHUNK_SIZE - 512;
+
+pub type StreamResult<T> = Result<T, StreamError>;
+
+#[derive(Error, Debug, Clone)]
+pub enum StreamError {
+    #[error("stream aborted by the sender: {0}")]
+    Aborted(String),
+    #[error("the sender disconnected before closing the stream")]
+    SenderDisconnected,
+    #[error("received invalid utf-8 on a text stream")]
+    InvalidUtf8,
+    #[error("chunks of the stream were lost")]
+    ChunksLost,
+}
+
+/// Metadata of a stream, sent before its content
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub id: String,
+    pub topic: String,
+    pub mime_type: String,
+    /// Creation time of the stream, in milliseconds since the UNIX epoch
+    pub timestamp: i64,
+    pub attributes: HashMap<String, String>,
+    /// Name of the content, only set on byte streams (e.g. a file name)
+    pub name: Option<String>,
+    pub sender_identity: ParticipantIdentity,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StreamKind {
+    Text,
+    Byte,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamControl {
+    Header {
+        stream_id: String,
+        kind: StreamKind,
+        topic: String,
+        mime_type: String,
+        timestamp: i64,
+        attributes: HashMap<String, String>,
+        name: Option<String>,
+    },
+    Trailer {
+        stream_id: String,
+        reason: Option<String>,
+    },
+}
+
+fn encode_control(control: &StreamControl) -> RoomResult<Vec<u8>> {
+    let json = serde_json::to_vec(control).map_err(|err| RoomError::Internal(err.to_string()))?;
+    let mut packet = Vec::with_capacity(1 + json.len());
+    packet.push(CONTROL_PACKET);
+    packet.extend_from_slice(&json);
+    Ok(packet)
+}
+
+fn generate_stream_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+fn encode_chunk(stream_id: &str, index: u64, content: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(stream_id.len(), STREAM_ID_LEN);
+    let mut packet = Vec::with_capacity(10 + stream_id.len() + content.len());
+    packet.push(CHUNK_PACKET);
+    packet.push(stream_id.len() as u8);
+    packet.extend_from_slice(stream_id.as_bytes());
+    packet.extend_from_slice(&index.to_be_bytes());
+    packet.extend_from_slice(content);
+    packet
+}
+
+/// Returns the stream id, the index and the content of a chunk packet (without the packet type)
+fn decode_chunk(packet: &[u8]) -> Option<(String, u64, &[u8])> {
+    let (&id_len, packet) = packet.split_first()?;
+    let id_len = id_len as usize;
+    if packet.len() < id_len + 8 {
+        return None;
+    }
+
+    let (stream_id, packet) = packet.split_at(id_len);
+    let stream_id = String::from_utf8(stream_id.to_vec()).ok()?;
+    let (index, content) = packet.split_at(8);
+    let index = u64::from_be_bytes(index.try_into().unwrap());
+    Some((stream_id, index, content))
+}
+
+/// Split a text on UTF-8 char boundaries so every chunk can be decoded on its own
+fn split_utf8(mut text: &str, max_size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    while !text.is_empty() {
+        let mut end = text.len().min(max_size);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let (chunk, rest) = text.split_at(end);
+        chunks.push(chunk);
+        text = rest;
+    }
+    chunks
+}
+
+struct StreamWriter {
+    local_participant: LocalParticipant,
+    stream_id: String,
+    next_index: u64,
+    closed: bool,
+}
+
+impl StreamWriter {
+    async fn open(
+        local_participant: LocalParticipant,
+        kind: StreamKind,
+        topic: String,
+        mime_type: String,
+        name: Option<String>,
+        attributes: HashMap<String, String>,
+    ) -> RoomResult<(Self, StreamInfo)> {
+        let info = StreamInfo {
+            id: generate_stream_id(),
+            topic,
+            mime_type,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as i64,
+            attributes,
+            name,
+            sender_identity: local_participant.identity(),
+        };
+
+        let header = StreamControl::Header {
+            stream_id: info.id.clone(),
+            kind,
+            topic: info.topic.clone(),
+            mime_type: info.mime_type.clone(),
+            timestamp: info.timestamp,
+            attributes: info.attributes.clone(),
+            name: info.name.clone(),
+        };
+
+        let writer = Self {
+            local_participant,
+            stream_id: info.id.clone(),
+            next_index: 0,
+            closed: false,
+        };
+        writer.send_packet(encode_control(&header)?).await?;
+        Ok((writer, info))
+    }
+
+    async fn write_chunk(&mut self, content: &[u8]) -> RoomResult<()> {
+        let packet = encode_chunk(&self.stream_id, self.next_index, content);
+        self.next_index += 1;
+        self.send_packet(packet).await
+    }
+
+    async fn close(mut self, reason: Option<String>) -> RoomResult<()> {
+        self.closed = true;
+        let trailer = StreamControl::Trailer {
+            stream_id: self.stream_id.clone(),
+            reason,
+        };
+        self.send_packet(encode_control(&trailer)?).await
+    }
+
+    async fn send_packet(&self, packet: Vec<u8>) -> RoomResult<()> {
+        self.local_participant
+            .send_user_packet(
+                packet,
+                DataPacketKind::Reliable,
+                Vec::new(),
+                Some(STREAM_TOPIC.to_owned()),
+            )
+            .await
+    }
+}
+
+impl Drop for StreamWriter {
+    // Abort the streams dropped without being closed, the readers would wait forever otherwise
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            log::warn!("stream {} dropped without being closed", self.stream_id);
+            return;
+        };
+
+        let trailer = StreamControl::Trailer {
+            stream_id: self.stream_id.clone(),
+            reason: Some("the writer was dropped without being closed".to_owned()),
+        };
+        let Ok(packet) = encode_control(&trailer) else {
+            return;
+        };
+
+        let local_participant = self.local_participant.clone();
+        runtime.spawn(async move {
+            let _ = local_participant
+                .send_user_packet(
+                    packet,
+                    DataPacketKind::Reliable,
+                    Vec::new(),
+                    Some(STREAM_TOPIC.to_owned()),
+                )
+                .await;
+        });
+    }
+}
+
+/// Created by [LocalParticipant::stream_text], the stream must be closed once the
+/// whole text has been written. Dropping the writer aborts the stream
+pub struct TextStreamWriter {
+    writer: StreamWriter,
+    info: StreamInfo,
+}
+
+impl TextStreamWriter {
+    pub(crate) async fn open(
+        local_participant: LocalParticipant,
+        topic: String,
+        attributes: HashMap<String, String>,
+    ) -> RoomResult<Self> {
+        let (writer, info) = StreamWriter::open(
+            local_participant,
+            StreamKind::Text,
+            topic,
+            "text/plain".to_owned(),
+            None,
+            attributes,
+        )
+        .await?;
+        Ok(Self { writer, info })
+    }
+
+    pub fn info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    pub async fn write(&mut self, text: &str) -> RoomResult<()> {
+        for chunk in split_utf8(text, STREAM_CHUNK_SIZE) {
+            self.writer.write_chunk(chunk.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn close(self) -> RoomResult<()> {
+        self.writer.close(None).await
+    }
+
+    /// Close the stream, the receivers get a StreamError::Aborted containing `reason`
+    pub async fn abort(self, reason: String) -> RoomResult<()> {
+        self.writer.close(Some(reason)).await
+    }
+}
+
+/// Created by [LocalParticipant::stream_bytes], the stream must be closed once every
+/// byte has been written. Dropping the writer aborts the stream
+pub struct ByteStreamWriter {
+    writer: StreamWriter,
+    info: StreamInfo,
+}
+
+impl ByteStreamWriter {
+    pub(crate) async fn open(
+        local_participant: LocalParticipant,
+        topic: String,
+        name: String,
+        mime_type: String,
+        attributes: HashMap<String, String>,
+    ) -> RoomResult<Self> {
+        let (writer, info) = StreamWriter::open(
+            local_participant,
+            StreamKind::Byte,
+            topic,
+            mime_type,
+            Some(name),
+            attributes,
+        )
+        .await?;
+        Ok(Self { writer, info })
+    }
+
+    pub fn info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    pub async fn write(&mut self, data: &[u8]) -> RoomResult<()> {
+        for chunk in data.chunks(STREAM_CHUNK_SIZE) {
+            self.writer.write_chunk(chunk).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn close(self) -> RoomResult<()> {
+        self.writer.close(None).await
+    }
+
+    /// Close the stream, the receivers get a StreamError::Aborted containing `reason`
+    pub async fn abort(self, reason: String) -> RoomResult<()> {
+        self.writer.close(Some(reason)).await
+    }
+}
+
+/// Yielded by [Room::register_text_stream_handler] for every incoming stream on the topic
+#[derive(Debug)]
+pub struct TextStreamReader {
+    info: StreamInfo,
+    chunk_rx: mpsc::UnboundedReceiver<StreamResult<Vec<u8>>>,
+}
+
+impl TextStreamReader {
+    pub fn info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    /// Returns None once the sender closed the stream
+    pub async fn read_chunk(&mut self) -> Option<StreamResult<String>> {
+        let chunk = self.chunk_rx.recv().await?;
+        Some(chunk.and_then(|chunk| String::from_utf8(chunk).map_err(|_| StreamError::InvalidUtf8)))
+    }
+
+    /// Wait for the stream to be closed and returns the whole text
+    pub async fn read_all(mut self) -> StreamResult<String> {
+        let mut text = String::new();
+        while let Some(chunk) = self.read_chunk().await {
+            text.push_str(&chunk?);
+        }
+        Ok(text)
+    }
+}
+
+/// Yielded by [Room::register_byte_stream_handler] for every incoming stream on the topic
+#[derive(Debug)]
+pub struct ByteStreamReader {
+    info: StreamInfo,
+    chunk_rx: mpsc::UnboundedReceiver<StreamResult<Vec<u8>>>,
+}
+
+impl ByteStreamReader {
+    pub fn info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    /// Returns None once the sender closed the stream
+    pub async fn read_chunk(&mut self) -> Option<StreamResult<Vec<u8>>> {
+        self.chunk_rx.recv().await
+    }
+
+    /// Wait for the stream to be closed and returns all the received bytes
+    pub async fn read_all(mut self) -> StreamResult<Vec<u8>> {
+        let mut data = Vec::new();
+        while let Some(chunk) = self.read_chunk().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+}
+
+struct OpenStream {
+    chunk_tx: mpsc::UnboundedSender<StreamResult<Vec<u8>>>,
+    next_index: u64,
+}
+
+/// Route the incoming stream packets to the readers, multiple streams can be
+/// received at the same time (their packets are interleaved)
+#[derive(Default)]
+pub(crate) struct IncomingStreams {
+    text_handlers: HashMap<String, mpsc::UnboundedSender<TextStreamReader>>,
+    byte_handlers: HashMap<String, mpsc::UnboundedSender<ByteStreamReader>>,
+    open_streams: HashMap<(ParticipantSid, String), OpenStream>,
+}
+
+impl IncomingStreams {
+    pub fn register_text_handler(
+        &mut self,
+        topic: String,
+    ) -> mpsc::UnboundedReceiver<TextStreamReader> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.text_handlers.insert(topic, tx);
+        rx
+    }
+
+    pub fn register_byte_handler(
+        &mut self,
+        topic: String,
+    ) -> mpsc::UnboundedReceiver<ByteStreamReader> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.byte_handlers.insert(topic, tx);
+        rx
+    }
+
+    pub fn handle_packet(
+        &mut self,
+        sender_sid: &ParticipantSid,
+        sender_identity: &ParticipantIdentity,
+        packet: &[u8],
+    ) {
+        let Some((&packet_type, packet)) = packet.split_first() else {
+            return;
+        };
+
+        match packet_type {
+            CONTROL_PACKET => match serde_json::from_slice::<StreamControl>(packet) {
+                Ok(control) => self.handle_control(sender_sid, sender_identity, control),
+                Err(err) => {
+                    log::warn!(
+                        "received an invalid stream packet from {}: {}",
+                        sender_identity,
+                        err
+                    );
+                }
+            },
+            CHUNK_PACKET => {
+                let Some((stream_id, index, content)) = decode_chunk(packet) else {
+                    log::warn!("received an invalid stream chunk from {}", sender_identity);
+                    return;
+                };
+
+                // Streams without handler are ignored
+                let key = (sender_sid.clone(), stream_id);
+                let Some(stream) = self.open_streams.get_mut(&key) else {
+                    return;
+                };
+
+                if index < stream.next_index {
+                    log::debug!("ignoring a duplicated chunk of the stream {}", key.1);
+                    return;
+                }
+
+                if index > stream.next_index {
+                    log::warn!("chunks of the stream {} were lost", key.1);
+                    let _ = stream.chunk_tx.send(Err(StreamError::ChunksLost));
+                    self.open_streams.remove(&key);
+                    return;
+                }
+
+                stream.next_index += 1;
+                if stream.chunk_tx.send(Ok(content.to_vec())).is_err() {
+                    self.open_streams.remove(&key); // The reader was dropped
+                }
+            }
+            _ => log::warn!("received an unknown stream packet type: {}", packet_type),
+        }
+    }
+
+    fn handle_control(
+        &mut self,
+        sender_sid: &ParticipantSid,
+        sender_identity: &ParticipantIdentity,
+        control: StreamControl,
+    ) {
+        match control {
+            StreamControl::Header {
+                stream_id,
+                kind,
+                topic,
+                mime_type,
+                timestamp,
+                attributes,
+                name,
+            } => {
+                if stream_id.len() > u8::MAX as usize {
+                    log::warn!(
+                        "received a stream with an invalid id from {}",
+                        sender_identity
+                    );
+                    return;
+                }
+
+                let (chunk_tx, chunk_rx) = mpsc::unbounded_channel();
+                let info = StreamInfo {
+                    id: stream_id.clone(),
+                    topic,
+                    mime_type,
+                    timestamp,
+                    attributes,
+                    name,
+                    sender_identity: sender_identity.clone(),
+                };
+
+                let topic = info.topic.clone();
+                let accepted = match kind {
+                    StreamKind::Text => Self::dispatch_reader(
+                        &mut self.text_handlers,
+                        &topic,
+                        TextStreamReader { info, chunk_rx },
+                    ),
+                    StreamKind::Byte => Self::dispatch_reader(
+                        &mut self.byte_handlers,
+                        &topic,
+                        ByteStreamReader { info, chunk_rx },
+                    ),
+                };
+
+                if accepted {
+                    self.open_streams.insert(
+                        (sender_sid.clone(), stream_id),
+                        OpenStream {
+                            chunk_tx,
+                            next_index: 0,
+                        },
+                    );
+                } else {
+                    log::debug!("no handler registered for the stream topic {}", topic);
+                }
+            }
+            StreamControl::Trailer { stream_id, reason } => {
+                let stream = self.open_streams.remove(&(sender_sid.clone(), stream_id));
+                if let (Some(stream), Some(reason)) = (stream, reason) {
+                    let _ = stream.chunk_tx.send(Err(StreamError::Aborted(reason)));
+                }
+            }
+        }
+    }
+
+    fn dispatch_reader<T>(
+        handlers: &mut HashMap<String, mpsc::UnboundedSender<T>>,
+        topic: &str,
+        reader: T,
+    ) -> bool {
+        let Some(handler) = handlers.get(topic) else {
+            return false;
+        };
+
+        if handler.send(reader).is_err() {
+            handlers.remove(topic); // The receiver was dropped
+            return false;
+        }
+        true
+    }
+
+    /// Fail the streams left open by a participant that left the room
+    pub fn remove_participant(&mut self, sid: &ParticipantSid) {
+        self.open_streams.retain(|(sender, _), stream| {
+            if sender != sid {
+                return true;
+            }
+
+            let _ = stream.chunk_tx.send(Err(StreamError::SenderDisconnected));
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(stream_id: &str, kind: StreamKind, topic: &str) -> Vec<u8> {
+        encode_control(&StreamControl::Header {
+            stream_id: stream_id.to_owned(),
+            kind,
+            topic: topic.to_owned(),
+            mime_type: "text/plain".to_owned(),
+            timestamp: 0,
+            attributes: HashMap::new(),
+            name: None,
+        })
+        .unwrap()
+    }
+
+    fn trailer(stream_id: &str, reason: Option<&str>) -> Vec<u8> {
+        encode_control(&StreamControl::Trailer {
+            stream_id: stream_id.to_owned(),
+            reason: reason.map(str::to_owned),
+        })
+        .unwrap()
+    }
+
+    fn sender() -> (ParticipantSid, ParticipantIdentity) {
+        ("PA_1".to_owned().into(), "alice".to_owned().into())
+    }
+
+    #[test]
+    fn test_split_utf8() {
+        assert!(split_utf8("", 4).is_empty());
+        assert_eq!(split_utf8("abcdef", 4), vec!["abcd", "ef"]);
+        // "é" is 2 bytes, it can't be split
+        assert_eq!(split_utf8("abcé", 4), vec!["abc", "é"]);
+        assert_eq!(split_utf8("ééé", 3), vec!["é", "é", "é"]);
+    }
+
+    #[test]
+    fn test_chunk_encoding() {
+        let stream_id = generate_stream_id();
+        let packet = encode_chunk(&stream_id, 42, b"hello");
+        assert_eq!(packet[0], CHUNK_PACKET);
+
+        let (id, index, content) = decode_chunk(&packet[1..]).unwrap();
+        assert_eq!(id, stream_id);
+        assert_eq!(index, 42);
+        assert_eq!(content, b"hello");
+
+        assert!(decode_chunk(&packet[1..packet.len() - 6]).is_none());
+        assert!(decode_chunk(&[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_incoming_text_stream() {
+        let (sid, identity) = sender();
+        let stream_id = generate_stream_id();
+        let mut streams = IncomingStreams::default();
+        let mut readers = streams.register_text_handler("chat".to_owned());
+
+        streams.handle_packet(
+            &sid,
+            &identity,
+            &header(&stream_id, StreamKind::Text, "chat"),
+        );
+        streams.handle_packet(&sid, &identity, &encode_chunk(&stream_id, 0, b"hello "));
+        // Duplicated chunks are ignored
+        streams.handle_packet(&sid, &identity, &encode_chunk(&stream_id, 0, b"hello "));
+        streams.handle_packet(&sid, &identity, &encode_chunk(&stream_id, 1, b"world"));
+        streams.handle_packet(&sid, &identity, &trailer(&stream_id, None));
+
+        let reader = readers.recv().await.unwrap();
+        assert_eq!(reader.info().sender_identity, identity);
+        assert_eq!(reader.read_all().await.unwrap(), "hello world");
+        assert!(streams.open_streams.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_incoming_stream_errors() {
+        let (sid, identity) = sender();
+        let mut streams = IncomingStreams::default();
+        let mut readers = streams.register_byte_handler("files".to_owned());
+
+        // Missing chunk
+        let lost_id = generate_stream_id();
+        streams.handle_packet(
+            &sid,
+            &identity,
+            &header(&lost_id, StreamKind::Byte, "files"),
+        );
+        streams.handle_packet(&sid, &identity, &encode_chunk(&lost_id, 1, b"data"));
+        let reader = readers.recv().await.unwrap();
+        assert!(matches!(
+            reader.read_all().await,
+            Err(StreamError::ChunksLost)
+        ));
+
+        // Aborted by the sender
+        let aborted_id = generate_stream_id();
+        streams.handle_packet(
+            &sid,
+            &identity,
+            &header(&aborted_id, StreamKind::Byte, "files"),
+        );
+        streams.handle_packet(&sid, &identity, &trailer(&aborted_id, Some("cancelled")));
+        let reader = readers.recv().await.unwrap();
+        assert!(
+            matches!(reader.read_all().await, Err(StreamError::Aborted(reason)) if reason == "cancelled")
+        );
+
+        // No handler for the topic
+        let ignored_id = generate_stream_id();
+        streams.handle_packet(
+            &sid,
+            &identity,
+            &header(&ignored_id, StreamKind::Byte, "other"),
+        );
+        assert!(streams.open_streams.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remove_participant_mid_stream() {
+        let (sid, identity) = sender();
+        let other_sid: ParticipantSid = "PA_2".to_owned().into();
+        let mut streams = IncomingStreams::default();
+        let mut readers = streams.register_byte_handler("files".to_owned());
+
+        let stream_id = generate_stream_id();
+        let other_id = generate_stream_id();
+        streams.handle_packet(
+            &sid,
+            &identity,
+            &header(&stream_id, StreamKind::Byte, "files"),
+        );
+        streams.handle_packet(
+            &other_sid,
+            &identity,
+            &header(&other_id, StreamKind::Byte, "files"),
+        );
+        streams.handle_packet(&sid, &identity, &encode_chunk(&stream_id, 0, b"data"));
+
+        streams.remove_participant(&sid);
+        assert_eq!(streams.open_streams.len(), 1);
+
+        let mut reader = readers.recv().await.unwrap();
+        assert_eq!(reader.read_chunk().await.unwrap().unwrap(), b"data");
+        assert!(matches!(
+            reader.read_chunk().await,
+            Some(Err(StreamError::SenderDisconnected))
+        ));
+    }
+}
@@ -1,26 +1,39 @@
+use self::reconnect::{FixedInterval, ReconnectMode, ReconnectPolicy};
 use self::track::RemoteTrack;
-use crate::participant::ConnectionQuality;
+use crate::participant::{ConnectionQuality, ParticipantPermissions};
 use crate::prelude::*;
+use crate::publication::PermissionStatus;
 use crate::rtc_engine::EngineError;
-use crate::rtc_engine::{EngineEvent, EngineEvents, EngineResult, RtcEngine};
-use livekit_api::signal_client::SignalOptions;
+use crate::rtc_engine::{EngineEvent, EngineEvents, EngineOptions, EngineResult, RtcEngine};
+use livekit_api::signal_client::{SignalOptions, JOIN_RESPONSE_TIMEOUT, SIGNAL_CONNECT_TIMEOUT};
 use livekit_protocol as proto;
 use livekit_protocol::observer::Dispatcher;
-use parking_lot::RwLock;
+use livekit_webrtc::prelude::{ContinualGatheringPolicy, IceTransportsType, RtcConfiguration};
+use parking_lot::{Mutex, RwLock};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::Mutex as AsyncMutex;
 use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
 
-pub use crate::rtc_engine::SimulateScenario;
+pub use crate::rtc_engine::{ConnectPhase, SimulateScenario};
+pub use data::{MAX_DATA_PAYLOAD_SIZE, RESERVED_TOPICS};
+pub use tokio_util::sync::CancellationToken;
 
+pub const ICE_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+pub const PUBLISHER_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+mod data;
+pub mod data_stream;
 pub mod id;
 pub mod options;
 pub mod participant;
 pub mod publication;
+pub mod reconnect;
+pub mod rpc;
 pub mod track;
 
 pub type RoomResult<T> = Result<T, RoomError>;
@@ -35,6 +48,16 @@ pub enum RoomError {
     TrackAlreadyPublished,
     #[error("already closed")]
     AlreadyClosed,
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("invalid options: {0}")]
+    InvalidOptions(String),
+    #[error("connection timed out during the {0:?} phase")]
+    Timeout(ConnectPhase),
+    #[error("request timed out: {0}")]
+    RequestTimeout(String),
+    #[error("connection cancelled")]
+    Cancelled,
 }
 
 #[derive(Clone, Debug)]
@@ -48,6 +71,11 @@ pub enum RoomEvent {
     LocalTrackUnpublished {
         publication: LocalTrackPublication,
     },
+    /// The track has been published again after a full reconnection, its sid changed
+    LocalTrackRepublished {
+        publication: LocalTrackPublication,
+        previous_sid: TrackSid,
+    },
     TrackSubscribed {
         track: RemoteTrack,
         publication: RemoteTrackPublication,
@@ -71,6 +99,12 @@ pub enum RoomEvent {
         error: track::TrackError,
         sid: TrackSid,
     },
+    /// The publisher changed the subscription permissions of the track
+    TrackSubscriptionPermissionChanged {
+        participant: RemoteParticipant,
+        publication: RemoteTrackPublication,
+        status: PermissionStatus,
+    },
     TrackMuted {
         participant: Participant,
         publication: TrackPublication,
@@ -86,15 +120,52 @@ pub enum RoomEvent {
         quality: ConnectionQuality,
         participant: Participant,
     },
+    ParticipantMetadataChanged {
+        participant: Participant,
+        old_metadata: String,
+        metadata: String,
+    },
+    ParticipantNameChanged {
+        participant: Participant,
+        old_name: String,
+        name: String,
+    },
+    ParticipantPermissionsChanged {
+        participant: Participant,
+        old_permissions: Option<ParticipantPermissions>,
+        permissions: ParticipantPermissions,
+    },
+    /// Only contains the attributes that changed, the removed ones have an empty value
+    ParticipantAttributesChanged {
+        participant: Participant,
+        changed_attributes: HashMap<String, String>,
+    },
+    RoomMetadataChanged {
+        old_metadata: String,
+        metadata: String,
+    },
     DataReceived {
         payload: Arc<Vec<u8>>,
+        topic: Option<String>,
         kind: DataPacketKind,
         participant: RemoteParticipant,
     },
+    /// The server refreshed the access token, it can be persisted to reconnect later
+    TokenRefreshed {
+        token: String,
+    },
     ConnectionStateChanged(ConnectionState),
     Connected,
-    Disconnected,
+    Disconnected {
+        reason: DisconnectReason,
+    },
     Reconnecting,
+    /// Sent before each reconnection attempt (The attempt starts after `delay`)
+    ReconnectAttempt {
+        attempt: u32,
+        mode: ReconnectMode,
+        delay: Duration,
+    },
     Reconnected,
 }
 
@@ -106,17 +177,99 @@ pub enum ConnectionState {
     Unknown,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DisconnectReason {
+    Unknown,
+    /// Room::close was called
+    ClientInitiated,
+    /// Another participant joined with the same identity
+    DuplicateIdentity,
+    ServerShutdown,
+    /// Removed with the server API (RoomService::RemoveParticipant)
+    ParticipantRemoved,
+    RoomDeleted,
+    /// The client tried to resume a session unknown to the server
+    StateMismatch,
+    JoinFailure,
+    /// The ReconnectPolicy gave up
+    ReconnectExhausted,
+}
+
+impl From<proto::DisconnectReason> for DisconnectReason {
+    fn from(reason: proto::DisconnectReason) -> Self {
+        match reason {
+            proto::DisconnectReason::ClientInitiated => Self::ClientInitiated,
+            proto::DisconnectReason::DuplicateIdentity => Self::DuplicateIdentity,
+            proto::DisconnectReason::ServerShutdown => Self::ServerShutdown,
+            proto::DisconnectReason::ParticipantRemoved => Self::ParticipantRemoved,
+            proto::DisconnectReason::RoomDeleted => Self::RoomDeleted,
+            proto::DisconnectReason::StateMismatch => Self::StateMismatch,
+            proto::DisconnectReason::JoinFailure => Self::JoinFailure,
+            _ => Self::Unknown,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum DataPacketKind {
     Lossy,
     Reliable,
 }
 
+/// Progress of [LocalParticipant::publish_data_with_progress]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DataProgress {
+    pub sent_bytes: usize,
+    pub total_bytes: usize,
+}
+
+/// Packet yielded by [Room::data_receiver]
+#[derive(Debug, Clone)]
+pub struct ReceivedData {
+    pub payload: Arc<Vec<u8>>,
+    pub topic: String,
+    pub kind: DataPacketKind,
+    pub participant: RemoteParticipant,
+}
+
 #[derive(Debug, Clone)]
 pub struct RoomOptions {
     pub auto_subscribe: bool,
     pub adaptive_stream: bool,
     pub dynacast: bool,
+    pub reconnect_policy: Arc<dyn ReconnectPolicy>,
+    /// The ICE servers are used in addition to the ones provided by the server.
+    /// A relay only transport requires at least one TURN server in the merged list
+    pub rtc_config: RtcConfiguration,
+    pub connect_options: ConnectOptions,
+}
+
+/// Timeouts of each phase of the connection, Room::connect fails with
+/// `RoomError::Timeout` containing the phase that timed out
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// Websocket handshake with the server (also used when resuming)
+    pub signal_timeout: Duration,
+    /// Time to wait for the JoinResponse once the websocket is open
+    pub join_timeout: Duration,
+    /// ICE connection of the PeerConnections, Room::connect returns once connected
+    pub ice_timeout: Duration,
+    /// Connection of the publisher PeerConnection, started on the first publication
+    pub publisher_timeout: Duration,
+    /// Abort Room::connect, it then fails with `RoomError::Cancelled`
+    pub cancellation_token: Option<CancellationToken>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            signal_timeout: SIGNAL_CONNECT_TIMEOUT,
+            join_timeout: JOIN_RESPONSE_TIMEOUT,
+            ice_timeout: ICE_CONNECT_TIMEOUT,
+            publisher_timeout: PUBLISHER_CONNECT_TIMEOUT,
+            cancellation_token: None,
+        }
+    }
 }
 
 impl Default for RoomOptions {
@@ -125,8 +278,60 @@ impl Default for RoomOptions {
             auto_subscribe: true,
             adaptive_stream: false,
             dynacast: false,
+            reconnect_policy: Arc::new(FixedInterval::default()),
+            rtc_config: RtcConfiguration {
+                ice_servers: vec![],
+                continual_gathering_policy: ContinualGatheringPolicy::GatherContinually,
+                ice_transport_type: IceTransportsType::All,
+            },
+            connect_options: ConnectOptions::default(),
+        }
+    }
+}
+
+fn validate_rtc_config(rtc_config: &RtcConfiguration) -> RoomResult<()> {
+    if rtc_config.ice_transport_type == IceTransportsType::None {
+        return Err(RoomError::InvalidOptions(
+            "the ICE transport type can't be None".to_owned(),
+        ));
+    }
+
+    for ice_server in &rtc_config.ice_servers {
+        if ice_server.urls.is_empty() {
+            return Err(RoomError::InvalidOptions(
+                "an ICE server has no urls".to_owned(),
+            ));
+        }
+
+        for url in &ice_server.urls {
+            let Some((scheme, _)) = url.split_once(':') else {
+                return Err(RoomError::InvalidOptions(format!(
+                    "invalid ICE server url: {}",
+                    url
+                )));
+            };
+
+            match scheme {
+                "stun" | "stuns" => {}
+                "turn" | "turns" => {
+                    if ice_server.username.is_empty() || ice_server.password.is_empty() {
+                        return Err(RoomError::InvalidOptions(format!(
+                            "the TURN server {} requires a username and a password",
+                            url
+                        )));
+                    }
+                }
+                _ => {
+                    return Err(RoomError::InvalidOptions(format!(
+                        "unsupported ICE server scheme: {}",
+                        url
+                    )))
+                }
+            }
         }
     }
+
+    Ok(())
 }
 
 struct RoomHandle {
@@ -155,30 +360,48 @@ impl Room {
         token: &str,
         options: RoomOptions,
     ) -> RoomResult<(Self, mpsc::UnboundedReceiver<RoomEvent>)> {
+        validate_rtc_config(&options.rtc_config)?;
+
         let (rtc_engine, engine_events) = RtcEngine::connect(
             url,
             token,
             SignalOptions {
                 auto_subscribe: options.auto_subscribe,
                 adaptive_stream: options.adaptive_stream,
-                ..Default::default()
+                connect_timeout: options.connect_options.signal_timeout,
+                join_timeout: options.connect_options.join_timeout,
+            },
+            EngineOptions {
+                rtc_config: options.rtc_config.clone(),
+                ice_timeout: options.connect_options.ice_timeout,
+                publisher_timeout: options.connect_options.publisher_timeout,
+                reconnect_policy: options.reconnect_policy.clone(),
+                cancellation_token: options.connect_options.cancellation_token.clone(),
             },
         )
-        .await?;
+        .await
+        .map_err(|err| match err {
+            EngineError::Timeout(phase) => RoomError::Timeout(phase),
+            EngineError::Cancelled => RoomError::Cancelled,
+            err => err.into(),
+        })?;
         let rtc_engine = Arc::new(rtc_engine);
 
         let join_response = rtc_engine.join_response();
         let pi = join_response.participant.unwrap().clone();
         let local_participant = LocalParticipant::new(
             rtc_engine.clone(),
-            pi.sid.into(),
-            pi.identity.into(),
-            pi.name,
-            pi.metadata,
+            pi.sid.clone().into(),
+            pi.identity.clone().into(),
+            pi.name.clone(),
+            pi.metadata.clone(),
+            pi.attributes.clone(),
         );
+        local_participant.update_info(pi); // Permissions
 
         let room_info = join_response.room.unwrap();
         let inner = Arc::new(RoomSession {
+            options,
             sid: room_info.sid.into(),
             name: room_info.name,
             info: RwLock::new(RoomInfo {
@@ -186,16 +409,88 @@ impl Room {
                 metadata: room_info.metadata,
             }),
             participants: Default::default(),
+            chunk_assembler: Default::default(),
+            topic_dispatchers: Default::default(),
+            incoming_streams: Default::default(),
             active_speakers: Default::default(),
             rtc_engine,
             local_participant,
             dispatcher: Default::default(),
         });
 
+        let dispatcher = inner.dispatcher.clone();
+        inner
+            .local_participant
+            .on_track_muted(move |participant, publication, _| {
+                dispatcher.dispatch(&RoomEvent::TrackMuted {
+                    participant,
+                    publication,
+                });
+            });
+
+        let dispatcher = inner.dispatcher.clone();
+        inner
+            .local_participant
+            .on_track_unmuted(move |participant, publication, _| {
+                dispatcher.dispatch(&RoomEvent::TrackUnmuted {
+                    participant,
+                    publication,
+                });
+            });
+
+        let dispatcher = inner.dispatcher.clone();
+        inner
+            .local_participant
+            .on_metadata_changed(move |participant, old_metadata, metadata| {
+                dispatcher.dispatch(&RoomEvent::ParticipantMetadataChanged {
+                    participant,
+                    old_metadata,
+                    metadata,
+                });
+            });
+
+        let dispatcher = inner.dispatcher.clone();
+        inner
+            .local_participant
+            .on_name_changed(move |participant, old_name, name| {
+                dispatcher.dispatch(&RoomEvent::ParticipantNameChanged {
+                    participant,
+                    old_name,
+                    name,
+                });
+            });
+
+        let dispatcher = inner.dispatcher.clone();
+        inner.local_participant.on_permissions_changed(
+            move |participant, old_permissions, permissions| {
+                dispatcher.dispatch(&RoomEvent::ParticipantPermissionsChanged {
+                    participant,
+                    old_permissions,
+                    permissions,
+                });
+            },
+        );
+
+        let dispatcher = inner.dispatcher.clone();
+        inner
+            .local_participant
+            .on_attributes_changed(move |participant, changed_attributes| {
+                dispatcher.dispatch(&RoomEvent::ParticipantAttributesChanged {
+                    participant,
+                    changed_attributes,
+                });
+            });
+
         for pi in join_response.other_participants {
             let participant = {
                 let pi = pi.clone();
-                inner.create_participant(pi.sid.into(), pi.identity.into(), pi.name, pi.metadata)
+                inner.create_participant(
+                    pi.sid.into(),
+                    pi.identity.into(),
+                    pi.name,
+                    pi.metadata,
+                    pi.attributes,
+                )
             };
             participant.update_info(pi.clone());
         }
@@ -222,6 +517,10 @@ impl Room {
             self.inner.close().await;
             let _ = handle.close_emitter.send(());
             let _ = handle.session_task.await;
+            // The session task may already have handled the engine Disconnected event,
+            // handle_disconnected only dispatches on the first transition
+            self.inner
+                .handle_disconnected(DisconnectReason::ClientInitiated);
             Ok(())
         } else {
             Err(RoomError::AlreadyClosed)
@@ -232,6 +531,40 @@ impl Room {
         self.inner.dispatcher.register()
     }
 
+    /// Receive the text streams opened on `topic` by the remote participants.
+    /// Registering the same topic again replaces the previous receiver
+    pub fn register_text_stream_handler(
+        &self,
+        topic: &str,
+    ) -> mpsc::UnboundedReceiver<data_stream::TextStreamReader> {
+        self.inner
+            .incoming_streams
+            .lock()
+            .register_text_handler(topic.to_owned())
+    }
+
+    /// Receive the byte streams opened on `topic` by the remote participants.
+    /// Registering the same topic again replaces the previous receiver
+    pub fn register_byte_stream_handler(
+        &self,
+        topic: &str,
+    ) -> mpsc::UnboundedReceiver<data_stream::ByteStreamReader> {
+        self.inner
+            .incoming_streams
+            .lock()
+            .register_byte_handler(topic.to_owned())
+    }
+
+    /// Receive the data packets published on `topic`
+    pub fn data_receiver(&self, topic: &str) -> mpsc::UnboundedReceiver<ReceivedData> {
+        self.inner
+            .topic_dispatchers
+            .lock()
+            .entry(topic.to_owned())
+            .or_default()
+            .register()
+    }
+
     pub fn sid(&self) -> RoomSid {
         self.inner.sid.clone()
     }
@@ -252,6 +585,11 @@ impl Room {
         self.inner.info.read().state
     }
 
+    /// Region of the server hosting the room, when connected to LiveKit Cloud
+    pub fn region(&self) -> Option<String> {
+        self.inner.rtc_engine.region()
+    }
+
     pub fn participants(&self) -> HashMap<ParticipantSid, RemoteParticipant> {
         self.inner.participants.read().clone()
     }
@@ -266,8 +604,21 @@ struct RoomInfo {
     state: ConnectionState,
 }
 
+impl RoomInfo {
+    /// Returns false if the room is already in this state
+    fn set_state(&mut self, state: ConnectionState) -> bool {
+        if self.state == state {
+            return false;
+        }
+
+        self.state = state;
+        true
+    }
+}
+
 pub(crate) struct RoomSession {
     rtc_engine: Arc<RtcEngine>,
+    options: RoomOptions,
     sid: RoomSid,
     name: String,
     info: RwLock<RoomInfo>,
@@ -275,6 +626,9 @@ pub(crate) struct RoomSession {
     active_speakers: RwLock<Vec<Participant>>,
     local_participant: LocalParticipant,
     participants: RwLock<HashMap<ParticipantSid, RemoteParticipant>>,
+    chunk_assembler: Mutex<data::ChunkAssembler>,
+    topic_dispatchers: Mutex<HashMap<String, Dispatcher<ReceivedData>>>,
+    incoming_streams: Mutex<data_stream::IncomingStreams>,
 }
 
 impl Debug for RoomSession {
@@ -317,6 +671,7 @@ impl RoomSession {
                 track,
                 stream,
                 receiver: _,
+                transceiver,
             } => {
                 let stream_id = stream.id();
                 let lk_stream_id = unpack_stream_id(&stream_id);
@@ -332,9 +687,15 @@ impl RoomSession {
                 let remote_participant = self.get_participant(&participant_sid.to_string().into());
 
                 if let Some(remote_participant) = remote_participant {
+                    let adaptive_stream = self.options.adaptive_stream;
                     tokio::spawn(async move {
                         remote_participant
-                            .add_subscribed_media_track(track_sid, track)
+                            .add_subscribed_media_track(
+                                track_sid,
+                                track,
+                                transceiver,
+                                adaptive_stream,
+                            )
                             .await;
                     });
                 } else {
@@ -355,21 +716,74 @@ impl RoomSession {
                 self.update_connection_state(ConnectionState::Connected);
                 self.dispatcher.dispatch(&RoomEvent::Reconnected);
 
-                // TODO(theomonnom): Update subscriptions settings
-                // TODO(theomonnom): Send sync state
+                tokio::spawn({
+                    let session = self.clone();
+                    async move {
+                        if let Err(err) = session.send_sync_state().await {
+                            log::error!("failed to send sync state: {:?}", err);
+                        }
+                        session.send_track_settings().await;
+                    }
+                });
             }
             EngineEvent::Restarting => self.handle_restarting(),
             EngineEvent::Restarted => self.handle_restarted(),
-            EngineEvent::Disconnected => self.handle_disconnected(),
+            EngineEvent::Disconnected { reason } => self.handle_disconnected(reason),
             EngineEvent::Data {
                 payload,
+                topic,
                 kind,
                 participant_sid,
             } => {
+                let participant_sid: ParticipantSid = participant_sid.into();
+                if topic.as_deref() == Some(rpc::RPC_TOPIC) {
+                    if let Some(participant) = self.get_participant(&participant_sid) {
+                        self.local_participant
+                            .handle_rpc_packet(&participant, &payload);
+                    }
+                    return Ok(());
+                }
+
+                if topic.as_deref() == Some(data_stream::STREAM_TOPIC) {
+                    if let Some(participant) = self.get_participant(&participant_sid) {
+                        self.incoming_streams.lock().handle_packet(
+                            &participant.sid(),
+                            &participant.identity(),
+                            &payload,
+                        );
+                    }
+                    return Ok(());
+                }
+
+                let chunk_topic = topic.as_deref().and_then(data::parse_chunk_topic);
+                let (payload, topic) = if let Some(topic) = chunk_topic {
+                    let payload = self.chunk_assembler.lock().push(&participant_sid, &payload);
+
+                    let Some(payload) = payload else {
+                        return Ok(()); // Wait for the other chunks
+                    };
+                    (payload, topic)
+                } else {
+                    (payload, topic)
+                };
+
                 let payload = Arc::new(payload);
-                if let Some(participant) = self.get_participant(&participant_sid.into()) {
+                if let Some(participant) = self.get_participant(&participant_sid) {
+                    if let Some(topic) = &topic {
+                        let dispatcher = self.topic_dispatchers.lock().get(topic).cloned();
+                        if let Some(dispatcher) = dispatcher {
+                            dispatcher.dispatch(&ReceivedData {
+                                payload: payload.clone(),
+                                topic: topic.clone(),
+                                kind,
+                                participant: participant.clone(),
+                            });
+                        }
+                    }
+
                     self.dispatcher.dispatch(&RoomEvent::DataReceived {
                         payload: payload.clone(),
+                        topic,
                         kind,
                         participant: participant.clone(),
                     });
@@ -381,6 +795,39 @@ impl RoomSession {
             EngineEvent::ConnectionQuality { updates } => {
                 self.handle_connection_quality_update(updates)
             }
+            EngineEvent::ReconnectAttempt {
+                attempt,
+                mode,
+                delay,
+            } => {
+                self.dispatcher.dispatch(&RoomEvent::ReconnectAttempt {
+                    attempt,
+                    mode,
+                    delay,
+                });
+            }
+            EngineEvent::RoomUpdate { room } => self.handle_room_update(room),
+            EngineEvent::SubscriptionPermissionUpdate { update } => {
+                self.handle_subscription_permission_update(update)
+            }
+            EngineEvent::SubscribedQualityUpdate { update } => {
+                self.handle_subscribed_quality_update(update)
+            }
+            EngineEvent::LocalTrackMuted { sid, muted } => {
+                self.handle_server_mute(sid.into(), muted)
+            }
+            EngineEvent::LocalTrackUnpublished { sid } => {
+                let local_participant = self.local_participant.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = local_participant.unpublish_track(sid.into(), true).await {
+                        log::error!("failed to unpublish the track: {:?}", err);
+                    }
+                });
+            }
+            EngineEvent::TokenRefreshed { token } => {
+                self.dispatcher
+                    .dispatch(&RoomEvent::TokenRefreshed { token });
+            }
         }
 
         Ok(())
@@ -394,12 +841,13 @@ impl RoomSession {
     /// Does nothing if the state is already the same
     /// Returns true if the state changed
     fn update_connection_state(&self, state: ConnectionState) -> bool {
+        // Checked and updated under the same lock, so concurrent callers (e.g Room::close and
+        // the engine Disconnected event) can't both see the transition
         let mut info = self.info.write();
-        if info.state == state {
+        if !info.set_state(state) {
             return false;
         }
 
-        info.state = state;
         self.dispatcher
             .dispatch(&RoomEvent::ConnectionStateChanged(state));
         return true;
@@ -434,7 +882,13 @@ impl RoomSession {
                 log::info!("Participant connected: {}", pi.sid);
                 let remote_participant = {
                     let pi = pi.clone();
-                    self.create_participant(pi.sid.into(), pi.identity.into(), pi.name, pi.metadata)
+                    self.create_participant(
+                        pi.sid.into(),
+                        pi.identity.into(),
+                        pi.name,
+                        pi.metadata,
+                        pi.attributes,
+                    )
                 };
 
                 let _ = self
@@ -446,6 +900,62 @@ impl RoomSession {
         }
     }
 
+    /// The room metadata changed
+    fn handle_room_update(&self, room_info: proto::Room) {
+        let old_metadata =
+            std::mem::replace(&mut self.info.write().metadata, room_info.metadata.clone());
+
+        if old_metadata != room_info.metadata {
+            self.dispatcher.dispatch(&RoomEvent::RoomMetadataChanged {
+                old_metadata,
+                metadata: room_info.metadata,
+            });
+        }
+    }
+
+    fn handle_subscription_permission_update(&self, update: proto::SubscriptionPermissionUpdate) {
+        let Some(participant) = self.get_participant(&update.participant_sid.into()) else {
+            log::warn!("received permission update for an unknown participant");
+            return;
+        };
+
+        let Some(publication) = participant.get_track_publication(&update.track_sid.into()) else {
+            log::warn!("received permission update for an unknown track");
+            return;
+        };
+
+        publication.set_allowed(update.allowed);
+    }
+
+    /// The server (or an admin using the server API) muted/unmuted one of our tracks
+    fn handle_server_mute(&self, sid: TrackSid, muted: bool) {
+        let Some(publication) = self.local_participant.get_track_publication(&sid) else {
+            log::warn!("received a mute request for an unknown track: {}", sid);
+            return;
+        };
+
+        self.local_participant
+            .set_muted_by_server(&publication, muted);
+    }
+
+    fn handle_subscribed_quality_update(&self, update: proto::SubscribedQualityUpdate) {
+        if !self.options.dynacast {
+            return;
+        }
+
+        let Some(publication) = self
+            .local_participant
+            .get_track_publication(&update.track_sid.into())
+        else {
+            log::warn!("received subscribed quality update for an unknown track");
+            return;
+        };
+
+        if let Err(err) = publication.set_subscribed_qualities(&update.subscribed_qualities) {
+            log::error!("failed to update the publishing layers: {:?}", err);
+        }
+    }
+
     /// Active speakers changed
     /// Update the participants & sort the active_speakers by audio_level
     fn handle_speakers_changed(&self, speakers_info: Vec<proto::SpeakerInfo>) {
@@ -511,11 +1021,21 @@ impl RoomSession {
     }
 
     fn handle_restarting(self: &Arc<Self>) {
-        // Remove existing participants/subscriptions on full reconnect
+        // The subscriber PeerConnection is closed on full reconnect, the remote tracks we received
+        // are no longer valid. The participants & publications are kept and synchronized when the
+        // new session is ready (See handle_restarted)
         let participants = self.participants.read().clone();
         for (_, participant) in participants.iter() {
-            self.clone()
-                .handle_participant_disconnect(participant.clone());
+            for (_, publication) in participant.tracks() {
+                let TrackPublication::Remote(publication) = publication else {
+                    continue;
+                };
+
+                if let Some(track) = publication.track() {
+                    track.disable();
+                    publication.set_track(None); // Fire TrackUnsubscribed
+                }
+            }
         }
 
         if self.update_connection_state(ConnectionState::Reconnecting) {
@@ -527,23 +1047,246 @@ impl RoomSession {
         // Full reconnect succeeded!
         let join_response = self.rtc_engine.join_response();
 
-        self.update_connection_state(ConnectionState::Connected);
-        self.dispatcher.dispatch(&RoomEvent::Reconnected);
-
         if let Some(pi) = join_response.participant {
             self.local_participant.update_info(pi); // The sid may have changed
         }
 
+        if let Some(room_info) = join_response.room {
+            self.handle_room_update(room_info);
+        }
+
+        // Participants who left the room while we were reconnecting
+        let participants = self.participants.read().clone();
+        for (sid, participant) in participants {
+            if !join_response
+                .other_participants
+                .iter()
+                .any(|pi| pi.sid == sid)
+            {
+                self.clone().handle_participant_disconnect(participant);
+            }
+        }
+
         self.handle_participant_update(join_response.other_participants);
 
-        // TODO(theomonnom): Synchronize states
-        // TODO(theomonnom): Room info changed?
-        // TODO(theomonnom): unpublish & republish tracks
+        tokio::spawn({
+            let session = self.clone();
+            async move {
+                session.send_subscription_preferences().await;
+                session.send_track_settings().await;
+                session.republish_tracks().await;
+
+                if let Err(err) = session
+                    .local_participant
+                    .resend_track_subscription_permissions()
+                    .await
+                {
+                    log::error!("failed to send the subscription permissions: {:?}", err);
+                }
+
+                session.update_connection_state(ConnectionState::Connected);
+                session.dispatcher.dispatch(&RoomEvent::Reconnected);
+            }
+        });
+    }
+
+    /// Tell the SFU which tracks we're publishing and which remote tracks we want
+    /// (The subscription only contains the tracks that differ from auto_subscribe)
+    async fn send_sync_state(&self) -> RoomResult<()> {
+        let auto_subscribe = self.options.auto_subscribe;
+        let mut participant_tracks = Vec::new();
+        for (psid, participant) in self.participants.read().iter() {
+            let track_sids: Vec<String> = participant
+                .tracks()
+                .into_iter()
+                .filter_map(|(tsid, publication)| {
+                    let TrackPublication::Remote(publication) = publication else {
+                        return None;
+                    };
+
+                    let desired = publication
+                        .subscription_preference()
+                        .unwrap_or(auto_subscribe);
+                    (desired != auto_subscribe).then_some(tsid.to_string())
+                })
+                .collect();
+
+            if !track_sids.is_empty() {
+                participant_tracks.push(proto::ParticipantTracks {
+                    participant_sid: psid.to_string(),
+                    track_sids,
+                });
+            }
+        }
+
+        let subscription = proto::UpdateSubscription {
+            track_sids: participant_tracks
+                .iter()
+                .flat_map(|tracks| tracks.track_sids.clone())
+                .collect(),
+            subscribe: !auto_subscribe,
+            participant_tracks,
+        };
+
+        let publish_tracks = self
+            .local_participant
+            .tracks()
+            .into_values()
+            .filter_map(|publication| {
+                let TrackPublication::Local(publication) = publication else {
+                    return None;
+                };
+
+                let track = publication.track();
+                let dimension = publication.dimension();
+                Some(proto::TrackPublishedResponse {
+                    cid: track.rtc_track().id(),
+                    track: Some(proto::TrackInfo {
+                        sid: publication.sid().to_string(),
+                        name: publication.name(),
+                        r#type: proto::TrackType::from(publication.kind()) as i32,
+                        source: proto::TrackSource::from(publication.source()) as i32,
+                        muted: track.is_muted(),
+                        width: dimension.0,
+                        height: dimension.1,
+                        simulcast: publication.simulcasted(),
+                        mime_type: publication.mime_type(),
+                        ..Default::default()
+                    }),
+                })
+            })
+            .collect();
+
+        self.rtc_engine
+            .send_sync_state(subscription, publish_tracks)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Send again the track settings changed by the user, the SFU lost them if the session
+    /// was restarted or if the signal connection was lost while they were sent
+    async fn send_track_settings(&self) {
+        let track_settings: Vec<proto::UpdateTrackSettings> = self
+            .participants
+            .read()
+            .values()
+            .flat_map(|participant| participant.tracks().into_values())
+            .filter_map(|publication| {
+                let TrackPublication::Remote(publication) = publication else {
+                    return None;
+                };
+                publication.track_settings()
+            })
+            .collect();
+
+        for track_settings in track_settings {
+            if let Err(err) = self
+                .rtc_engine
+                .send_request(proto::signal_request::Message::TrackSetting(track_settings))
+                .await
+            {
+                log::error!("failed to send track settings: {:?}", err);
+            }
+        }
+    }
+
+    /// The new session only knows about the auto_subscribe option.
+    /// Send again the subscriptions explicitly requested by the user
+    async fn send_subscription_preferences(&self) {
+        let mut subscribe = Vec::new();
+        let mut unsubscribe = Vec::new();
+
+        for (psid, participant) in self.participants.read().iter() {
+            for (tsid, publication) in participant.tracks() {
+                let TrackPublication::Remote(publication) = publication else {
+                    continue;
+                };
+
+                match publication.subscription_preference() {
+                    Some(true) if !self.options.auto_subscribe => {
+                        subscribe.push((psid.clone(), tsid))
+                    }
+                    Some(false) if self.options.auto_subscribe => {
+                        unsubscribe.push((psid.clone(), tsid))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (tracks, subscribe) in [(subscribe, true), (unsubscribe, false)] {
+            if tracks.is_empty() {
+                continue;
+            }
+
+            let update_subscription = proto::UpdateSubscription {
+                track_sids: tracks.iter().map(|(_, tsid)| tsid.to_string()).collect(),
+                subscribe,
+                participant_tracks: tracks
+                    .iter()
+                    .map(|(psid, tsid)| proto::ParticipantTracks {
+                        participant_sid: psid.to_string(),
+                        track_sids: vec![tsid.to_string()],
+                    })
+                    .collect(),
+            };
+
+            if let Err(err) = self
+                .rtc_engine
+                .send_request(proto::signal_request::Message::Subscription(
+                    update_subscription,
+                ))
+                .await
+            {
+                log::error!("failed to restore the subscriptions: {:?}", err);
+            }
+        }
+    }
+
+    /// Republish the local tracks on the new session (The old publisher is closed)
+    async fn republish_tracks(&self) {
+        let publications = self.local_participant.tracks();
+        if publications.is_empty() {
+            return;
+        }
+
+        for (_, publication) in publications {
+            let TrackPublication::Local(publication) = publication else {
+                continue;
+            };
+
+            match self.local_participant.republish_track(&publication).await {
+                Ok(previous_sid) => {
+                    log::info!(
+                        "republished track {} (previous sid: {})",
+                        publication.sid(),
+                        previous_sid
+                    );
+                    self.dispatcher.dispatch(&RoomEvent::LocalTrackRepublished {
+                        publication,
+                        previous_sid,
+                    });
+                }
+                Err(err) => {
+                    log::error!("failed to republish track {}: {}", publication.sid(), err);
+                    self.dispatcher
+                        .dispatch(&RoomEvent::LocalTrackUnpublished { publication });
+                }
+            }
+        }
+
+        if let Err(err) = self.rtc_engine.negotiate_publisher().await {
+            log::error!(
+                "failed to negotiate the publisher after republishing: {}",
+                err
+            );
+        }
     }
 
-    fn handle_disconnected(&self) {
+    fn handle_disconnected(&self, reason: DisconnectReason) {
         if self.update_connection_state(ConnectionState::Disconnected) {
-            self.dispatcher.dispatch(&RoomEvent::Disconnected);
+            self.dispatcher
+                .dispatch(&RoomEvent::Disconnected { reason });
         }
     }
 
@@ -555,6 +1298,7 @@ impl RoomSession {
         identity: ParticipantIdentity,
         name: String,
         metadata: String,
+        attributes: HashMap<String, String>,
     ) -> RemoteParticipant {
         let participant = RemoteParticipant::new(
             self.rtc_engine.clone(),
@@ -562,6 +1306,7 @@ impl RoomSession {
             identity,
             name,
             metadata,
+            attributes,
         );
 
         let dispatcher = self.dispatcher.clone();
@@ -607,7 +1352,71 @@ impl RoomSession {
             });
         });
 
+        let dispatcher = self.dispatcher.clone();
+        participant.on_track_subscription_permission_changed(
+            move |participant, publication, status| {
+                dispatcher.dispatch(&RoomEvent::TrackSubscriptionPermissionChanged {
+                    participant,
+                    publication,
+                    status,
+                });
+            },
+        );
+
+        let dispatcher = self.dispatcher.clone();
+        participant.on_track_muted(move |participant, publication, _| {
+            dispatcher.dispatch(&RoomEvent::TrackMuted {
+                participant,
+                publication,
+            });
+        });
+
+        let dispatcher = self.dispatcher.clone();
+        participant.on_track_unmuted(move |participant, publication, _| {
+            dispatcher.dispatch(&RoomEvent::TrackUnmuted {
+                participant,
+                publication,
+            });
+        });
+
+        let dispatcher = self.dispatcher.clone();
+        participant.on_metadata_changed(move |participant, old_metadata, metadata| {
+            dispatcher.dispatch(&RoomEvent::ParticipantMetadataChanged {
+                participant,
+                old_metadata,
+                metadata,
+            });
+        });
+
+        let dispatcher = self.dispatcher.clone();
+        participant.on_name_changed(move |participant, old_name, name| {
+            dispatcher.dispatch(&RoomEvent::ParticipantNameChanged {
+                participant,
+                old_name,
+                name,
+            });
+        });
+
+        let dispatcher = self.dispatcher.clone();
+        participant.on_permissions_changed(move |participant, old_permissions, permissions| {
+            dispatcher.dispatch(&RoomEvent::ParticipantPermissionsChanged {
+                participant,
+                old_permissions,
+                permissions,
+            });
+        });
+
+        let dispatcher = self.dispatcher.clone();
+        participant.on_attributes_changed(move |participant, changed_attributes| {
+            dispatcher.dispatch(&RoomEvent::ParticipantAttributesChanged {
+                participant,
+                changed_attributes,
+            });
+        });
+
         self.participants.write().insert(sid, participant.clone());
+        self.local_participant
+            .handle_participant_connected(&participant);
 
         participant
     }
@@ -620,6 +1429,14 @@ impl RoomSession {
         }
 
         self.participants.write().remove(&remote_participant.sid());
+        self.local_participant
+            .handle_participant_disconnected(&remote_participant);
+        self.incoming_streams
+            .lock()
+            .remove_participant(&remote_participant.sid());
+        self.chunk_assembler
+            .lock()
+            .remove_participant(&remote_participant.sid());
         self.dispatcher
             .dispatch(&RoomEvent::ParticipantDisconnected(remote_participant));
     }
@@ -639,3 +1456,102 @@ fn unpack_stream_id(stream_id: &str) -> Option<(&str, &str)> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use livekit_webrtc::prelude::IceServer;
+
+    fn rtc_config(
+        ice_transport_type: IceTransportsType,
+        ice_servers: Vec<IceServer>,
+    ) -> RtcConfiguration {
+        RtcConfiguration {
+            ice_servers,
+            continual_gathering_policy: ContinualGatheringPolicy::GatherContinually,
+            ice_transport_type,
+        }
+    }
+
+    fn ice_server(url: &str, username: &str, password: &str) -> IceServer {
+        IceServer {
+            urls: vec![url.to_owned()],
+            username: username.to_owned(),
+            password: password.to_owned(),
+        }
+    }
+
+    fn is_invalid(rtc_config: RtcConfiguration) -> bool {
+        matches!(
+            validate_rtc_config(&rtc_config),
+            Err(RoomError::InvalidOptions(_))
+        )
+    }
+
+    #[test]
+    fn test_validate_default_config() {
+        assert!(validate_rtc_config(&RoomOptions::default().rtc_config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_ice_servers() {
+        assert!(validate_rtc_config(&rtc_config(
+            IceTransportsType::All,
+            vec![
+                ice_server("stun:stun.example.com:3478", "", ""),
+                ice_server("turns:turn.example.com:443", "user", "pass"),
+            ],
+        ))
+        .is_ok());
+
+        assert!(is_invalid(rtc_config(
+            IceTransportsType::All,
+            vec![IceServer {
+                urls: vec![],
+                username: String::default(),
+                password: String::default(),
+            }],
+        )));
+        assert!(is_invalid(rtc_config(
+            IceTransportsType::All,
+            vec![ice_server("turn.example.com", "user", "pass")],
+        )));
+        assert!(is_invalid(rtc_config(
+            IceTransportsType::All,
+            vec![ice_server("http://turn.example.com", "user", "pass")],
+        )));
+        assert!(is_invalid(rtc_config(
+            IceTransportsType::All,
+            vec![ice_server("turn:turn.example.com:3478", "", "")],
+        )));
+    }
+
+    #[test]
+    fn test_validate_transport_type() {
+        assert!(is_invalid(rtc_config(IceTransportsType::None, vec![])));
+
+        // The TURN servers can be provided by the server, they're checked after the JoinResponse
+        assert!(validate_rtc_config(&rtc_config(IceTransportsType::Relay, vec![])).is_ok());
+        assert!(validate_rtc_config(&rtc_config(
+            IceTransportsType::Relay,
+            vec![ice_server("turn:turn.example.com:3478", "user", "pass")],
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_disconnected_once() {
+        let mut info = RoomInfo {
+            metadata: String::default(),
+            state: ConnectionState::Connected,
+        };
+
+        // Engine Disconnected event, then Room::close
+        assert!(info.set_state(ConnectionState::Disconnected));
+        assert!(!info.set_state(ConnectionState::Disconnected));
+        assert_eq!(info.state, ConnectionState::Disconnected);
+
+        assert!(info.set_state(ConnectionState::Reconnecting));
+        assert!(info.set_state(ConnectionState::Disconnected));
+    }
+}
@@ -1,18 +1,29 @@
 use super::ConnectionQuality;
 use super::ParticipantInner;
+use super::{ParticipantKind, ParticipantPermissions};
+use crate::data_stream::{ByteStreamWriter, TextStreamWriter};
 use crate::options;
 use crate::options::compute_video_encodings;
 use crate::options::video_layers_from_encodings;
 use crate::options::TrackPublishOptions;
 use crate::prelude::*;
+use crate::room::data;
+use crate::rpc::{self, RpcError, RpcErrorCode, RpcInvocationData};
 use crate::rtc_engine::RtcEngine;
-use crate::DataPacketKind;
+use crate::{DataPacketKind, DataProgress};
 use livekit_protocol as proto;
 use livekit_webrtc::rtp_parameters::RtpEncodingParameters;
 use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::time::sleep;
+
+const METADATA_UPDATE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Default)]
 struct LocalEvents {
@@ -22,8 +33,59 @@ struct LocalEvents {
         Mutex<Option<Box<dyn Fn(LocalParticipant, LocalTrackPublication) + Send>>>,
 }
 
+/// Restrict the tracks a remote participant is allowed to subscribe to
+#[derive(Debug, Clone, Default)]
+pub struct ParticipantTrackPermission {
+    pub participant_identity: ParticipantIdentity,
+    /// Allow the participant to subscribe to every track, `allowed_track_sids` is then ignored
+    pub allow_all: bool,
+    pub allowed_track_sids: Vec<TrackSid>,
+}
+
+impl From<ParticipantTrackPermission> for proto::TrackPermission {
+    fn from(permission: ParticipantTrackPermission) -> Self {
+        Self {
+            participant_identity: permission.participant_identity.into(),
+            all_tracks: permission.allow_all,
+            track_sids: permission
+                .allowed_track_sids
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// An UpdateParticipantMetadata request waiting for the server to echo the new values
+struct PendingInfoUpdate {
+    name: String,
+    metadata: String,
+    attributes: HashMap<String, String>,
+    tx: oneshot::Sender<()>,
+}
+
+impl PendingInfoUpdate {
+    /// Whether the participant info contains the requested values
+    fn is_applied(&self, name: &str, metadata: &str, attributes: &HashMap<String, String>) -> bool {
+        self.name == name
+            && self.metadata == metadata
+            && self.attributes.iter().all(|(key, value)| {
+                // An empty value removes the attribute
+                attributes
+                    .get(key)
+                    .map_or(value.is_empty(), |current| current == value)
+            })
+    }
+}
+
 struct LocalInfo {
     events: LocalEvents,
+    pending_updates: Mutex<Vec<PendingInfoUpdate>>,
+    // The info updates are sent one at a time, so they don't overwrite each other
+    info_update_lock: AsyncMutex<()>,
+    subscription_permission: Mutex<Option<proto::SubscriptionPermission>>,
+    rpc: Mutex<rpc::RpcState>,
 }
 
 #[derive(Clone)]
@@ -49,17 +111,32 @@ impl LocalParticipant {
         identity: ParticipantIdentity,
         name: String,
         metadata: String,
+        attributes: HashMap<String, String>,
     ) -> Self {
         Self {
-            inner: super::new_inner(rtc_engine, sid, identity, name, metadata),
+            inner: super::new_inner(rtc_engine, sid, identity, name, metadata, attributes),
             local: Arc::new(LocalInfo {
                 events: LocalEvents::default(),
+                pending_updates: Default::default(),
+                info_update_lock: Default::default(),
+                subscription_permission: Default::default(),
+                rpc: Default::default(),
             }),
         }
     }
 
     pub(crate) fn update_info(self: &Self, info: proto::ParticipantInfo) {
         super::update_info(&self.inner, &Participant::Local(self.clone()), info);
+
+        let (name, metadata, attributes) = (self.name(), self.metadata(), self.attributes());
+        let mut pending_updates = self.local.pending_updates.lock();
+        for pending in std::mem::take(&mut *pending_updates) {
+            if pending.is_applied(&name, &metadata, &attributes) {
+                let _ = pending.tx.send(());
+            } else if !pending.tx.is_closed() {
+                pending_updates.push(pending);
+            }
+        }
     }
 
     pub(crate) fn set_speaking(&self, speaking: bool) {
@@ -90,6 +167,50 @@ impl LocalParticipant {
         *self.local.events.local_track_unpublished.lock() = Some(Box::new(handler));
     }
 
+    pub(crate) fn on_track_muted(
+        &self,
+        handler: impl Fn(Participant, TrackPublication, Track) + Send + 'static,
+    ) {
+        super::on_track_muted(&self.inner, handler);
+    }
+
+    pub(crate) fn on_track_unmuted(
+        &self,
+        handler: impl Fn(Participant, TrackPublication, Track) + Send + 'static,
+    ) {
+        super::on_track_unmuted(&self.inner, handler);
+    }
+
+    pub(crate) fn on_metadata_changed(
+        &self,
+        handler: impl Fn(Participant, String, String) + Send + 'static,
+    ) {
+        super::on_metadata_changed(&self.inner, handler);
+    }
+
+    pub(crate) fn on_name_changed(
+        &self,
+        handler: impl Fn(Participant, String, String) + Send + 'static,
+    ) {
+        super::on_name_changed(&self.inner, handler);
+    }
+
+    pub(crate) fn on_attributes_changed(
+        &self,
+        handler: impl Fn(Participant, HashMap<String, String>) + Send + 'static,
+    ) {
+        super::on_attributes_changed(&self.inner, handler);
+    }
+
+    pub(crate) fn on_permissions_changed(
+        &self,
+        handler: impl Fn(Participant, Option<ParticipantPermissions>, ParticipantPermissions)
+            + Send
+            + 'static,
+    ) {
+        super::on_permissions_changed(&self.inner, handler);
+    }
+
     pub(crate) fn add_publication(&self, publication: TrackPublication) {
         super::add_publication(&self.inner, &Participant::Local(self.clone()), publication);
     }
@@ -104,6 +225,102 @@ impl LocalParticipant {
         track: LocalTrack,
         options: TrackPublishOptions,
     ) -> RoomResult<LocalTrackPublication> {
+        if let Some(permissions) = self.permissions() {
+            if !permissions.can_publish_source(options.source) {
+                return Err(RoomError::PermissionDenied(format!(
+                    "the participant isn't allowed to publish a {:?} track",
+                    options.source
+                )));
+            }
+        }
+
+        let track_info = self.add_track_sender(&track, &options).await?;
+        let publication = LocalTrackPublication::new(track_info, track.clone(), options);
+
+        // The offers of tracks published together are coalesced, wait for the answer
+        // so the track is ready to flow when returning
+        if let Err(err) = self.inner.rtc_engine.negotiate_publisher().await {
+            // The server already accepted the track, mute it so it isn't expected to flow
+            // and remove the sender from the publisher
+            super::send_mute_request(self.inner.rtc_engine.clone(), publication.sid(), true);
+            self.remove_track_sender(&track).await;
+
+            if let Err(err) = self.inner.rtc_engine.negotiate_publisher().await {
+                log::error!(
+                    "failed to negotiate the removal of {}: {}",
+                    publication.sid(),
+                    err
+                );
+            }
+            return Err(err.into());
+        }
+
+        self.add_publication(TrackPublication::Local(publication.clone()));
+
+        if let Some(local_track_published) = self.local.events.local_track_published.lock().as_ref()
+        {
+            local_track_published(self.clone(), publication.clone());
+        }
+
+        Ok(publication)
+    }
+
+    /// Publish again an existing publication on the current RtcSession.
+    /// This is used after a full reconnection, the previous PeerConnections are closed so the
+    /// track needs a new sender. The source, the publish options and the mute state are kept.
+    /// Returns the sid the publication had before being republished.
+    pub(crate) async fn republish_track(
+        &self,
+        publication: &LocalTrackPublication,
+    ) -> RoomResult<TrackSid> {
+        let previous_sid = publication.sid();
+        let track = publication.track();
+
+        self.inner.tracks.write().remove(&previous_sid);
+        track.set_transceiver(None); // The sender was owned by the closed publisher
+
+        let track_info = match self
+            .add_track_sender(&track, &publication.publish_options())
+            .await
+        {
+            Ok(track_info) => track_info,
+            Err(err) => {
+                // Same cleanup as unpublish_track, the caller negotiates the publisher once
+                // every track is republished
+                self.remove_track_sender(&track).await;
+                publication.set_track(None);
+                return Err(err);
+            }
+        };
+        publication.update_info(track_info);
+        self.add_publication(TrackPublication::Local(publication.clone()));
+
+        Ok(previous_sid)
+    }
+
+    /// Remove the sender of a track that couldn't be published,
+    /// the publisher must then be negotiated again
+    async fn remove_track_sender(&self, track: &LocalTrack) {
+        if let Some(transceiver) = track.transceiver() {
+            if let Err(err) = self
+                .inner
+                .rtc_engine
+                .remove_track(transceiver.sender())
+                .await
+            {
+                log::error!("failed to remove the sender of {}: {}", track.sid(), err);
+            }
+        }
+        track.set_transceiver(None);
+    }
+
+    /// Send the AddTrackRequest and create the RtpSender for this track.
+    /// The negotiation of the publisher isn't started here
+    async fn add_track_sender(
+        &self,
+        track: &LocalTrack,
+        options: &TrackPublishOptions,
+    ) -> RoomResult<proto::TrackInfo> {
         let mut req = proto::AddTrackRequest {
             cid: track.rtc_track().id(),
             name: track.name().clone(),
@@ -116,7 +333,7 @@ impl LocalParticipant {
         };
 
         let mut encodings = Vec::default();
-        match &track {
+        match track {
             LocalTrack::Video(video_track) => {
                 // Get the video dimension
                 // TODO(theomonnom): Use MediaStreamTrack::getSettings() on web
@@ -124,7 +341,7 @@ impl LocalParticipant {
                 req.width = resolution.width;
                 req.height = resolution.height;
 
-                encodings = compute_video_encodings(req.width, req.height, &options);
+                encodings = compute_video_encodings(req.width, req.height, options);
                 req.layers = video_layers_from_encodings(req.width, req.height, &encodings);
             }
             LocalTrack::Audio(_audio_track) => {
@@ -141,34 +358,21 @@ impl LocalParticipant {
             }
         }
         let track_info = self.inner.rtc_engine.add_track(req).await?;
-        let publication = LocalTrackPublication::new(track_info.clone(), track.clone());
-        track.update_info(track_info); // Update sid + source
+        track.update_info(track_info.clone()); // Update sid + source
 
         log::debug!("publishing track with cid {:?}", track.rtc_track().id());
         let transceiver = self
             .inner
             .rtc_engine
-            .create_sender(track.clone(), options, encodings)
+            .create_sender(track.clone(), options.clone(), encodings)
             .await?;
 
         track.set_transceiver(Some(transceiver));
-        track.enable();
-
-        tokio::spawn({
-            let rtc_engine = self.inner.rtc_engine.clone();
-            async move {
-                let _ = rtc_engine.negotiate_publisher().await;
-            }
-        });
-
-        self.add_publication(TrackPublication::Local(publication.clone()));
-
-        if let Some(local_track_published) = self.local.events.local_track_published.lock().as_ref()
-        {
-            local_track_published(self.clone(), publication.clone());
+        if !track.is_muted() {
+            track.enable();
         }
 
-        Ok(publication)
+        Ok(track_info)
     }
 
     pub async fn unpublish_track(
@@ -210,12 +414,104 @@ impl LocalParticipant {
         data: Vec<u8>,
         kind: DataPacketKind,
         destination_sids: Vec<String>,
+        topic: Option<String>,
+    ) -> RoomResult<()> {
+        self.publish_data_with_progress(data, kind, destination_sids, topic, |_| {})
+            .await
+    }
+
+    /// Payloads larger than a single packet are split into chunks and reassembled by the
+    /// receivers, this is only possible on the reliable channel.
+    /// The topics used by the SDK are reserved (see [crate::RESERVED_TOPICS]).
+    /// `on_progress` is called every time a packet has been sent
+    pub async fn publish_data_with_progress(
+        &self,
+        data: Vec<u8>,
+        kind: DataPacketKind,
+        destination_sids: Vec<String>,
+        topic: Option<String>,
+        on_progress: impl Fn(DataProgress) + Send,
+    ) -> RoomResult<()> {
+        if let Some(permissions) = self.permissions() {
+            if !permissions.can_publish_data {
+                return Err(RoomError::PermissionDenied(
+                    "the participant isn't allowed to publish data".to_owned(),
+                ));
+            }
+        }
+
+        if let Some(topic) = topic
+            .as_deref()
+            .filter(|topic| data::is_reserved_topic(topic))
+        {
+            return Err(RoomError::InvalidOptions(format!(
+                "the topic {} is reserved",
+                topic
+            )));
+        }
+
+        let total_bytes = data.len();
+        if total_bytes > data::MAX_DATA_PAYLOAD_SIZE {
+            return Err(RoomError::InvalidOptions(format!(
+                "payloads larger than {} bytes can't be published",
+                data::MAX_DATA_PAYLOAD_SIZE
+            )));
+        }
+
+        if total_bytes <= data::MAX_CHUNK_SIZE {
+            self.send_user_packet(data, kind, destination_sids, topic)
+                .await?;
+            on_progress(DataProgress {
+                sent_bytes: total_bytes,
+                total_bytes,
+            });
+            return Ok(());
+        }
+
+        if kind != DataPacketKind::Reliable {
+            return Err(RoomError::InvalidOptions(format!(
+                "payloads larger than {} bytes can only be sent reliably",
+                data::MAX_CHUNK_SIZE
+            )));
+        }
+
+        let chunk_topic = data::chunk_topic(topic.as_deref());
+        let message_id = rand::random::<u64>();
+        let mut sent_bytes = 0;
+        for (chunk, size) in data::encode_chunks(message_id, &data)
+            .zip(data.chunks(data::MAX_CHUNK_SIZE).map(<[u8]>::len))
+        {
+            self.send_user_packet(
+                chunk,
+                kind,
+                destination_sids.clone(),
+                Some(chunk_topic.clone()),
+            )
+            .await?;
+
+            sent_bytes += size;
+            on_progress(DataProgress {
+                sent_bytes,
+                total_bytes,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn send_user_packet(
+        &self,
+        payload: Vec<u8>,
+        kind: DataPacketKind,
+        destination_sids: Vec<String>,
+        topic: Option<String>,
     ) -> RoomResult<()> {
         let data = proto::DataPacket {
             kind: kind as i32,
             value: Some(proto::data_packet::Value::User(proto::UserPacket {
-                payload: data,
-                destination_sids: destination_sids.to_owned(),
+                payload,
+                destination_sids,
+                topic,
                 ..Default::default()
             })),
         };
@@ -227,6 +523,364 @@ impl LocalParticipant {
             .map_err(Into::into)
     }
 
+    /// Open a text stream on `topic`, the receivers get the stream using
+    /// [Room::register_text_stream_handler]
+    pub async fn stream_text(
+        &self,
+        topic: String,
+        attributes: HashMap<String, String>,
+    ) -> RoomResult<TextStreamWriter> {
+        TextStreamWriter::open(self.clone(), topic, attributes).await
+    }
+
+    /// Open a byte stream on `topic` (e.g. to send a file), the receivers get the stream
+    /// using [Room::register_byte_stream_handler]
+    pub async fn stream_bytes(
+        &self,
+        topic: String,
+        name: String,
+        mime_type: String,
+        attributes: HashMap<String, String>,
+    ) -> RoomResult<ByteStreamWriter> {
+        ByteStreamWriter::open(self.clone(), topic, name, mime_type, attributes).await
+    }
+
+    /// Update the metadata of the local participant, requires the `can_update_own_metadata` grant.
+    /// Resolves once the server has acknowledged the new metadata
+    pub async fn set_metadata(&self, metadata: String) -> RoomResult<()> {
+        self.update_metadata(None, Some(metadata), HashMap::new())
+            .await
+    }
+
+    /// Update the name of the local participant, requires the `can_update_own_metadata` grant.
+    /// Resolves once the server has acknowledged the new name
+    pub async fn set_name(&self, name: String) -> RoomResult<()> {
+        self.update_metadata(Some(name), None, HashMap::new()).await
+    }
+
+    /// Update a subset of the attributes of the local participant, the other attributes are
+    /// kept. An empty value removes the attribute.
+    /// Requires the `can_update_own_metadata` grant, resolves once the server has acknowledged
+    /// the new attributes
+    pub async fn set_attributes(&self, attributes: HashMap<String, String>) -> RoomResult<()> {
+        self.update_metadata(None, None, attributes).await
+    }
+
+    /// The fields left to None keep their current value
+    async fn update_metadata(
+        &self,
+        name: Option<String>,
+        metadata: Option<String>,
+        attributes: HashMap<String, String>,
+    ) -> RoomResult<()> {
+        let can_update_metadata = self
+            .inner
+            .info
+            .read()
+            .permissions
+            .as_ref()
+            .map_or(false, |permissions| permissions.can_update_metadata);
+
+        if !can_update_metadata {
+            return Err(RoomError::PermissionDenied(
+                "the token doesn't allow to update the participant metadata".to_owned(),
+            ));
+        }
+
+        // The current values are read once the previous update is acknowledged
+        let _guard = self.local.info_update_lock.lock().await;
+        let name = name.unwrap_or_else(|| self.name());
+        let metadata = metadata.unwrap_or_else(|| self.metadata());
+
+        let (tx, rx) = oneshot::channel();
+        let pending = PendingInfoUpdate {
+            name: name.clone(),
+            metadata: metadata.clone(),
+            attributes: attributes.clone(),
+            tx,
+        };
+
+        if pending.is_applied(&self.name(), &self.metadata(), &self.attributes()) {
+            return Ok(());
+        }
+        self.local.pending_updates.lock().push(pending);
+
+        let res = self
+            .inner
+            .rtc_engine
+            .send_request(proto::signal_request::Message::UpdateMetadata(
+                proto::UpdateParticipantMetadata {
+                    name,
+                    metadata,
+                    attributes,
+                    ..Default::default()
+                },
+            ))
+            .await;
+
+        let res = match res {
+            // Wait for the ParticipantUpdate containing the new values
+            Ok(()) => tokio::select! {
+                Ok(_) = rx => Ok(()),
+                _ = sleep(METADATA_UPDATE_TIMEOUT) => {
+                    Err(RoomError::RequestTimeout("no response received from the server for the metadata update".to_owned()))
+                },
+                else => Err(RoomError::Internal("metadata update cancelled".to_owned())),
+            },
+            Err(err) => {
+                drop(rx);
+                Err(err.into())
+            }
+        };
+
+        if res.is_err() {
+            // The receiver is dropped, remove the update we were waiting for
+            self.local
+                .pending_updates
+                .lock()
+                .retain(|pending| !pending.tx.is_closed());
+        }
+
+        res
+    }
+
+    /// Control who can subscribe to the tracks of the local participant.
+    /// When `all_allowed` is true, every participant can subscribe and `permissions` is ignored
+    pub async fn set_track_subscription_permissions(
+        &self,
+        all_allowed: bool,
+        permissions: Vec<ParticipantTrackPermission>,
+    ) -> RoomResult<()> {
+        let subscription_permission = proto::SubscriptionPermission {
+            all_participants: all_allowed,
+            track_permissions: permissions.into_iter().map(Into::into).collect(),
+        };
+
+        *self.local.subscription_permission.lock() = Some(subscription_permission.clone());
+        self.inner
+            .rtc_engine
+            .send_request(proto::signal_request::Message::SubscriptionPermission(
+                subscription_permission,
+            ))
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Send again the subscription permissions after a full reconnection (new session)
+    pub(crate) async fn resend_track_subscription_permissions(&self) -> RoomResult<()> {
+        let subscription_permission = self.local.subscription_permission.lock().clone();
+        if let Some(subscription_permission) = subscription_permission {
+            self.inner
+                .rtc_engine
+                .send_request(proto::signal_request::Message::SubscriptionPermission(
+                    subscription_permission,
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Register a method that the remote participants can call using perform_rpc.
+    /// Registering the same method again replaces the previous handler
+    pub fn register_rpc_method<F, Fut>(&self, method: String, handler: F)
+    where
+        F: Fn(RpcInvocationData) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, RpcError>> + Send + 'static,
+    {
+        let handler: rpc::RpcHandler = Arc::new(move |data| Box::pin(handler(data)));
+        self.local.rpc.lock().handlers.insert(method, handler);
+    }
+
+    pub fn unregister_rpc_method(&self, method: &str) {
+        self.local.rpc.lock().handlers.remove(method);
+    }
+
+    /// Call a method registered by a remote participant.
+    /// Fails with ConnectionTimeout if the request isn't acknowledged by the recipient and with
+    /// ResponseTimeout if the response isn't received within `response_timeout`
+    pub async fn perform_rpc(
+        &self,
+        destination_identity: ParticipantIdentity,
+        method: String,
+        payload: String,
+        response_timeout: Duration,
+    ) -> Result<String, RpcError> {
+        if payload.len() > rpc::MAX_RPC_PAYLOAD_BYTES {
+            return Err(RpcError::built_in(
+                RpcErrorCode::RequestPayloadTooLarge,
+                None,
+            ));
+        }
+
+        let request_id = format!("{:032x}", rand::random::<u128>());
+        let handle = self
+            .local
+            .rpc
+            .lock()
+            .start_request(request_id.clone(), destination_identity)?;
+
+        let request = rpc::RpcMessage::Request {
+            id: request_id.clone(),
+            method,
+            payload,
+            response_timeout_ms: response_timeout.as_millis() as u64,
+        };
+
+        let res = match self
+            .send_rpc_message(&request, handle.destination_sid.clone())
+            .await
+        {
+            Ok(_) => rpc::wait_response(handle, rpc::RPC_ACK_TIMEOUT, response_timeout).await,
+            Err(err) => Err(RpcError::built_in(
+                RpcErrorCode::SendFailed,
+                Some(err.to_string()),
+            )),
+        };
+
+        self.local.rpc.lock().finish_request(&request_id);
+        res
+    }
+
+    /// Handle a packet received on the RPC topic (request, ack or response)
+    pub(crate) fn handle_rpc_packet(&self, caller: &RemoteParticipant, payload: &[u8]) {
+        let message = match serde_json::from_slice::<rpc::RpcMessage>(payload) {
+            Ok(message) => message,
+            Err(err) => {
+                log::warn!(
+                    "received an invalid rpc packet from {}: {}",
+                    caller.identity(),
+                    err
+                );
+                return;
+            }
+        };
+
+        match message {
+            rpc::RpcMessage::Request {
+                id,
+                method,
+                payload,
+                response_timeout_ms,
+            } => {
+                let local_participant = self.clone();
+                let data = RpcInvocationData {
+                    request_id: id,
+                    caller_identity: caller.identity(),
+                    payload,
+                    response_timeout: Duration::from_millis(response_timeout_ms),
+                };
+                let caller_sid = caller.sid();
+                tokio::spawn(async move {
+                    local_participant
+                        .handle_rpc_request(caller_sid, method, data)
+                        .await;
+                });
+            }
+            rpc::RpcMessage::Ack { request_id } => {
+                self.local.rpc.lock().handle_ack(&request_id);
+            }
+            rpc::RpcMessage::Response {
+                request_id,
+                payload,
+                error,
+            } => {
+                let res = match error {
+                    Some(error) => Err(error),
+                    None => Ok(payload.unwrap_or_default()),
+                };
+
+                if !self.local.rpc.lock().handle_response(&request_id, res) {
+                    log::warn!(
+                        "received a response for an unknown rpc request {}",
+                        request_id
+                    );
+                }
+            }
+        }
+    }
+
+    async fn handle_rpc_request(
+        &self,
+        caller_sid: ParticipantSid,
+        method: String,
+        data: RpcInvocationData,
+    ) {
+        let request_id = data.request_id.clone();
+        let ack = rpc::RpcMessage::Ack {
+            request_id: request_id.clone(),
+        };
+        if let Err(err) = self.send_rpc_message(&ack, caller_sid.clone()).await {
+            log::error!("failed to send the rpc ack: {:?}", err);
+        }
+
+        let handler = self.local.rpc.lock().handlers.get(&method).cloned();
+        let res = rpc::run_handler(handler, data).await;
+
+        let (payload, error) = match res {
+            Ok(payload) => (Some(payload), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        let response = rpc::RpcMessage::Response {
+            request_id,
+            payload,
+            error,
+        };
+        if let Err(err) = self.send_rpc_message(&response, caller_sid).await {
+            log::error!("failed to send the rpc response: {:?}", err);
+        }
+    }
+
+    async fn send_rpc_message(
+        &self,
+        message: &rpc::RpcMessage,
+        destination_sid: ParticipantSid,
+    ) -> RoomResult<()> {
+        let payload =
+            serde_json::to_vec(message).map_err(|err| RoomError::Internal(err.to_string()))?;
+
+        self.send_user_packet(
+            payload,
+            DataPacketKind::Reliable,
+            vec![destination_sid.into()],
+            Some(rpc::RPC_TOPIC.to_owned()),
+        )
+        .await
+    }
+
+    /// Called by the Room to keep track of the participants that can be called with perform_rpc
+    pub(crate) fn handle_participant_connected(&self, participant: &RemoteParticipant) {
+        self.local
+            .rpc
+            .lock()
+            .add_participant(participant.identity(), participant.sid());
+    }
+
+    /// Fail the pending requests sent to a participant that left the room
+    pub(crate) fn handle_participant_disconnected(&self, participant: &RemoteParticipant) {
+        self.local
+            .rpc
+            .lock()
+            .remove_participant(&participant.identity(), &participant.sid());
+    }
+
+    /// Apply a mute requested by the server, the MuteTrackRequest isn't sent back
+    pub(crate) fn set_muted_by_server(&self, publication: &LocalTrackPublication, muted: bool) {
+        let track = publication.track();
+        if !track.update_muted(muted) {
+            return;
+        }
+
+        super::server_muted(
+            &self.inner,
+            &Participant::Local(self.clone()),
+            TrackPublication::Local(publication.clone()),
+            track.into(),
+            muted,
+        );
+    }
+
     pub fn get_track_publication(&self, sid: &TrackSid) -> Option<LocalTrackPublication> {
         self.inner.tracks.read().get(sid).map(|track| {
             if let TrackPublication::Local(local) = track {
@@ -253,6 +907,19 @@ impl LocalParticipant {
         self.inner.info.read().metadata.clone()
     }
 
+    pub fn attributes(&self) -> HashMap<String, String> {
+        self.inner.info.read().attributes.clone()
+    }
+
+    pub fn kind(&self) -> ParticipantKind {
+        self.inner.info.read().kind
+    }
+
+    /// None until the server sent the permissions of the participant
+    pub fn permissions(&self) -> Option<ParticipantPermissions> {
+        self.inner.info.read().permissions.clone()
+    }
+
     pub fn is_speaking(&self) -> bool {
         self.inner.info.read().speaking
     }
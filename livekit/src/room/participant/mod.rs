@@ -21,6 +21,38 @@ pub enum ConnectionQuality {
     Poor,
 }
 
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum ParticipantKind {
+    #[default]
+    Standard,
+    Ingress,
+    Egress,
+    Sip,
+    Agent,
+}
+
+/// Permissions granted to a participant by its token (or updated by the server API)
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ParticipantPermissions {
+    pub can_subscribe: bool,
+    pub can_publish: bool,
+    pub can_publish_data: bool,
+    /// Sources the participant can publish, every source is allowed when empty
+    pub can_publish_sources: Vec<TrackSource>,
+    pub can_update_metadata: bool,
+    /// Hidden participants aren't visible to the other participants
+    pub hidden: bool,
+    /// The participant is a recorder (e.g. an egress)
+    pub recorder: bool,
+}
+
+impl ParticipantPermissions {
+    pub fn can_publish_source(&self, source: TrackSource) -> bool {
+        self.can_publish
+            && (self.can_publish_sources.is_empty() || self.can_publish_sources.contains(&source))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Participant {
     Local(LocalParticipant),
@@ -34,6 +66,9 @@ impl Participant {
         pub fn identity(self: &Self) -> ParticipantIdentity;
         pub fn name(self: &Self) -> String;
         pub fn metadata(self: &Self) -> String;
+        pub fn attributes(self: &Self) -> HashMap<String, String>;
+        pub fn kind(self: &Self) -> ParticipantKind;
+        pub fn permissions(self: &Self) -> Option<ParticipantPermissions>;
         pub fn is_speaking(self: &Self) -> bool;
         pub fn audio_level(self: &Self) -> f32;
         pub fn connection_quality(self: &Self) -> ConnectionQuality;
@@ -55,15 +90,26 @@ struct ParticipantInfo {
     pub identity: ParticipantIdentity,
     pub name: String,
     pub metadata: String,
+    pub attributes: HashMap<String, String>,
     pub speaking: bool,
     pub audio_level: f32,
     pub connection_quality: ConnectionQuality,
+    pub kind: ParticipantKind,
+    pub permissions: Option<ParticipantPermissions>,
 }
 
 #[derive(Default)]
 struct ParticipantEvents {
     track_muted: Mutex<Option<Box<dyn Fn(Participant, TrackPublication, Track) + Send>>>,
     track_unmuted: Mutex<Option<Box<dyn Fn(Participant, TrackPublication, Track) + Send>>>,
+    metadata_changed: Mutex<Option<Box<dyn Fn(Participant, String, String) + Send>>>,
+    name_changed: Mutex<Option<Box<dyn Fn(Participant, String, String) + Send>>>,
+    attributes_changed: Mutex<Option<Box<dyn Fn(Participant, HashMap<String, String>) + Send>>>,
+    permissions_changed: Mutex<
+        Option<
+            Box<dyn Fn(Participant, Option<ParticipantPermissions>, ParticipantPermissions) + Send>,
+        >,
+    >,
 }
 
 pub(super) struct ParticipantInner {
@@ -79,6 +125,7 @@ pub(super) fn new_inner(
     identity: ParticipantIdentity,
     name: String,
     metadata: String,
+    attributes: HashMap<String, String>,
 ) -> Arc<ParticipantInner> {
     Arc::new(ParticipantInner {
         rtc_engine,
@@ -87,9 +134,12 @@ pub(super) fn new_inner(
             identity,
             name,
             metadata,
+            attributes,
             speaking: false,
             audio_level: 0.0,
             connection_quality: ConnectionQuality::Unknown,
+            kind: ParticipantKind::default(),
+            permissions: None,
         }),
         tracks: Default::default(),
         events: Default::default(),
@@ -98,14 +148,100 @@ pub(super) fn new_inner(
 
 pub(super) fn update_info(
     inner: &Arc<ParticipantInner>,
-    _participant: &Participant,
+    participant: &Participant,
     new_info: proto::ParticipantInfo,
 ) {
     let mut info = inner.info.write();
     info.sid = new_info.sid.into();
-    info.name = new_info.name;
     info.identity = new_info.identity.into();
-    info.metadata = new_info.metadata; // TODO(theomonnom): callback MetadataChanged
+    info.kind = new_info.kind().into();
+    let permissions = new_info
+        .permission
+        .clone()
+        .map(ParticipantPermissions::from);
+    let old_permissions = std::mem::replace(&mut info.permissions, permissions.clone());
+    let old_name = std::mem::replace(&mut info.name, new_info.name.clone());
+    let old_metadata = std::mem::replace(&mut info.metadata, new_info.metadata.clone());
+    let old_attributes = std::mem::replace(&mut info.attributes, new_info.attributes.clone());
+    drop(info);
+
+    // Callbacks are called after releasing the lock, they can read the participant info
+    if old_name != new_info.name {
+        if let Some(cb) = inner.events.name_changed.lock().as_ref() {
+            cb(participant.clone(), old_name, new_info.name);
+        }
+    }
+
+    if old_metadata != new_info.metadata {
+        if let Some(cb) = inner.events.metadata_changed.lock().as_ref() {
+            cb(participant.clone(), old_metadata, new_info.metadata);
+        }
+    }
+
+    if let Some(permissions) = permissions {
+        if old_permissions.as_ref() != Some(&permissions) {
+            if let Some(cb) = inner.events.permissions_changed.lock().as_ref() {
+                cb(participant.clone(), old_permissions, permissions);
+            }
+        }
+    }
+
+    let changed_attributes = diff_attributes(&old_attributes, &new_info.attributes);
+    if !changed_attributes.is_empty() {
+        if let Some(cb) = inner.events.attributes_changed.lock().as_ref() {
+            cb(participant.clone(), changed_attributes);
+        }
+    }
+}
+
+/// Returns the attributes that were added or updated, the removed ones have an empty value
+fn diff_attributes(
+    old: &HashMap<String, String>,
+    new: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut changed: HashMap<String, String> = new
+        .iter()
+        .filter(|(key, value)| old.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            changed.insert(key.clone(), String::new());
+        }
+    }
+
+    changed
+}
+
+pub(super) fn on_metadata_changed(
+    inner: &Arc<ParticipantInner>,
+    handler: impl Fn(Participant, String, String) + Send + 'static,
+) {
+    *inner.events.metadata_changed.lock() = Some(Box::new(handler));
+}
+
+pub(super) fn on_attributes_changed(
+    inner: &Arc<ParticipantInner>,
+    handler: impl Fn(Participant, HashMap<String, String>) + Send + 'static,
+) {
+    *inner.events.attributes_changed.lock() = Some(Box::new(handler));
+}
+
+pub(super) fn on_permissions_changed(
+    inner: &Arc<ParticipantInner>,
+    handler: impl Fn(Participant, Option<ParticipantPermissions>, ParticipantPermissions)
+        + Send
+        + 'static,
+) {
+    *inner.events.permissions_changed.lock() = Some(Box::new(handler));
+}
+
+pub(super) fn on_name_changed(
+    inner: &Arc<ParticipantInner>,
+    handler: impl Fn(Participant, String, String) + Send + 'static,
+) {
+    *inner.events.name_changed.lock() = Some(Box::new(handler));
 }
 
 pub(super) fn set_speaking(
@@ -160,18 +296,117 @@ pub(super) fn add_publication(
     tracks.insert(publication.sid(), publication.clone());
 
     let events = inner.events.clone();
+    let rtc_engine = inner.rtc_engine.clone();
     let particiant = participant.clone();
     publication.on_muted(move |publication, track| {
+        if !publication.is_remote() {
+            send_mute_request(rtc_engine.clone(), publication.sid(), true);
+        }
+
         if let Some(cb) = events.track_muted.lock().as_ref() {
             cb(particiant.clone(), publication, track);
         }
     });
 
     let events = inner.events.clone();
+    let rtc_engine = inner.rtc_engine.clone();
     let participant = participant.clone();
     publication.on_unmuted(move |publication, track| {
+        if !publication.is_remote() {
+            send_mute_request(rtc_engine.clone(), publication.sid(), false);
+        }
+
         if let Some(cb) = events.track_unmuted.lock().as_ref() {
             cb(participant.clone(), publication, track);
         }
     });
 }
+
+/// Emit the mute events of a track muted by the server, the SFU isn't notified back
+pub(super) fn server_muted(
+    inner: &Arc<ParticipantInner>,
+    participant: &Participant,
+    publication: TrackPublication,
+    track: Track,
+    muted: bool,
+) {
+    let events = if muted {
+        inner.events.track_muted.lock()
+    } else {
+        inner.events.track_unmuted.lock()
+    };
+
+    if let Some(cb) = events.as_ref() {
+        cb(participant.clone(), publication, track);
+    }
+}
+
+/// Tell the SFU that a local track has been muted/unmuted
+fn send_mute_request(rtc_engine: Arc<RtcEngine>, sid: TrackSid, muted: bool) {
+    tokio::spawn(async move {
+        let mute_request = proto::MuteTrackRequest {
+            sid: sid.to_string(),
+            muted,
+        };
+
+        if let Err(err) = rtc_engine
+            .send_request(proto::signal_request::Message::Mute(mute_request))
+            .await
+        {
+            log::error!("failed to send the mute request for {}: {:?}", sid, err);
+        }
+    });
+}
+
+pub(super) fn on_track_muted(
+    inner: &Arc<ParticipantInner>,
+    handler: impl Fn(Participant, TrackPublication, Track) + Send + 'static,
+) {
+    *inner.events.track_muted.lock() = Some(Box::new(handler));
+}
+
+pub(super) fn on_track_unmuted(
+    inner: &Arc<ParticipantInner>,
+    handler: impl Fn(Participant, TrackPublication, Track) + Send + 'static,
+) {
+    *inner.events.track_unmuted.lock() = Some(Box::new(handler));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_attributes() {
+        let old = attributes(&[("kept", "1"), ("changed", "1"), ("removed", "1")]);
+        let new = attributes(&[("kept", "1"), ("changed", "2"), ("added", "1")]);
+
+        assert_eq!(
+            diff_attributes(&old, &new),
+            attributes(&[("changed", "2"), ("added", "1"), ("removed", "")])
+        );
+    }
+
+    #[test]
+    fn test_diff_attributes_unchanged() {
+        let old = attributes(&[("key", "value")]);
+        assert!(diff_attributes(&old, &old).is_empty());
+        assert!(diff_attributes(&HashMap::new(), &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_attributes_all_removed() {
+        let old = attributes(&[("a", "1"), ("b", "2")]);
+        assert_eq!(
+            diff_attributes(&old, &HashMap::new()),
+            attributes(&[("a", ""), ("b", "")])
+        );
+    }
+}
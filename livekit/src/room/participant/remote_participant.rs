@@ -1,6 +1,7 @@
 use super::TrackKind;
-use super::{ConnectionQuality, ParticipantInner};
+use super::{ConnectionQuality, ParticipantInner, ParticipantKind, ParticipantPermissions};
 use crate::prelude::*;
+use crate::publication::PermissionStatus;
 use crate::rtc_engine::RtcEngine;
 use crate::track::TrackError;
 use livekit_protocol as proto;
@@ -10,6 +11,7 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::runtime::Handle;
 use tokio::time::timeout;
 
 const ADD_TRACK_TIMEOUT: Duration = Duration::from_secs(5);
@@ -24,6 +26,9 @@ struct RemoteEvents {
         Mutex<Option<Box<dyn Fn(RemoteParticipant, RemoteTrackPublication, RemoteTrack) + Send>>>,
     track_subscription_failed:
         Mutex<Option<Box<dyn Fn(RemoteParticipant, TrackSid, TrackError) + Send>>>,
+    track_subscription_permission_changed: Mutex<
+        Option<Box<dyn Fn(RemoteParticipant, RemoteTrackPublication, PermissionStatus) + Send>>,
+    >,
 }
 
 struct RemoteInfo {
@@ -53,9 +58,10 @@ impl RemoteParticipant {
         identity: ParticipantIdentity,
         name: String,
         metadata: String,
+        attributes: HashMap<String, String>,
     ) -> Self {
         Self {
-            inner: super::new_inner(rtc_engine, sid, identity, name, metadata),
+            inner: super::new_inner(rtc_engine, sid, identity, name, metadata, attributes),
             remote: Arc::new(RemoteInfo {
                 events: Default::default(),
             }),
@@ -66,6 +72,8 @@ impl RemoteParticipant {
         &self,
         sid: TrackSid,
         media_track: MediaStreamTrack,
+        transceiver: RtpTransceiver,
+        adaptive_stream: bool,
     ) {
         let wait_publication = {
             let participant = self.clone();
@@ -103,6 +111,10 @@ impl RemoteParticipant {
                             remote_publication.name(),
                             rtc_track,
                         );
+
+                        if adaptive_stream {
+                            remote_publication.enable_adaptive_stream(&video_track);
+                        }
                         RemoteTrack::Video(video_track)
                     } else {
                         unreachable!()
@@ -112,6 +124,8 @@ impl RemoteParticipant {
 
             log::debug!("starting track: {:?}", sid);
 
+            track.set_transceiver(Some(transceiver));
+
             //track.set_muted(remote_publication.is_muted());
             track.update_info(proto::TrackInfo {
                 sid: remote_publication.sid().to_string(),
@@ -232,6 +246,63 @@ impl RemoteParticipant {
             Some(Box::new(track_subscription_failed));
     }
 
+    pub(crate) fn on_track_subscription_permission_changed(
+        &self,
+        permission_changed: impl Fn(RemoteParticipant, RemoteTrackPublication, PermissionStatus)
+            + Send
+            + 'static,
+    ) {
+        *self
+            .remote
+            .events
+            .track_subscription_permission_changed
+            .lock() = Some(Box::new(permission_changed));
+    }
+
+    pub(crate) fn on_track_muted(
+        &self,
+        handler: impl Fn(Participant, TrackPublication, Track) + Send + 'static,
+    ) {
+        super::on_track_muted(&self.inner, handler);
+    }
+
+    pub(crate) fn on_track_unmuted(
+        &self,
+        handler: impl Fn(Participant, TrackPublication, Track) + Send + 'static,
+    ) {
+        super::on_track_unmuted(&self.inner, handler);
+    }
+
+    pub(crate) fn on_metadata_changed(
+        &self,
+        handler: impl Fn(Participant, String, String) + Send + 'static,
+    ) {
+        super::on_metadata_changed(&self.inner, handler);
+    }
+
+    pub(crate) fn on_name_changed(
+        &self,
+        handler: impl Fn(Participant, String, String) + Send + 'static,
+    ) {
+        super::on_name_changed(&self.inner, handler);
+    }
+
+    pub(crate) fn on_attributes_changed(
+        &self,
+        handler: impl Fn(Participant, HashMap<String, String>) + Send + 'static,
+    ) {
+        super::on_attributes_changed(&self.inner, handler);
+    }
+
+    pub(crate) fn on_permissions_changed(
+        &self,
+        handler: impl Fn(Participant, Option<ParticipantPermissions>, ParticipantPermissions)
+            + Send
+            + 'static,
+    ) {
+        super::on_permissions_changed(&self.inner, handler);
+    }
+
     pub(crate) fn set_speaking(&self, speaking: bool) {
         super::set_speaking(&self.inner, &Participant::Remote(self.clone()), speaking);
     }
@@ -265,7 +336,7 @@ impl RemoteParticipant {
                     let tsid = publication.sid().0.clone();
                     let update_subscription = proto::UpdateSubscription {
                         track_sids: vec![tsid.clone()],
-                        subscribe: publication.is_subscribed(),
+                        subscribe: publication.subscription_preference().unwrap_or(true),
                         participant_tracks: vec![proto::ParticipantTracks {
                             participant_sid: psid,
                             track_sids: vec![tsid.clone()],
@@ -281,6 +352,27 @@ impl RemoteParticipant {
             }
         });
 
+        // The settings setters are sync and can be called outside of the runtime
+        publication.on_track_settings_update_needed({
+            let rtc_engine = self.inner.rtc_engine.clone();
+            let handle = Handle::current();
+            move |publication| {
+                let Some(track_settings) = publication.track_settings() else {
+                    return;
+                };
+
+                let rtc_engine = rtc_engine.clone();
+                handle.spawn(async move {
+                    if let Err(err) = rtc_engine
+                        .send_request(proto::signal_request::Message::TrackSetting(track_settings))
+                        .await
+                    {
+                        log::error!("failed to send track settings: {:?}", err);
+                    }
+                });
+            }
+        });
+
         publication.on_subscribed({
             let events = self.remote.events.clone();
             let participant = self.clone();
@@ -300,6 +392,18 @@ impl RemoteParticipant {
                 }
             }
         });
+
+        publication.on_permission_status_changed({
+            let events = self.remote.events.clone();
+            let participant = self.clone();
+            move |publication, _, status| {
+                if let Some(permission_changed) =
+                    events.track_subscription_permission_changed.lock().as_ref()
+                {
+                    permission_changed(participant.clone(), publication, status);
+                }
+            }
+        });
     }
 
     pub(crate) fn remove_publication(&self, sid: &TrackSid) {
@@ -312,8 +416,10 @@ impl RemoteParticipant {
             };
 
             publication.on_subscription_update_needed(|_| {});
+            publication.on_track_settings_update_needed(|_| {});
             publication.on_subscribed(|_, _| {});
             publication.on_unsubscribed(|_, _| {});
+            publication.on_permission_status_changed(|_, _, _| {});
         }
     }
 
@@ -342,6 +448,19 @@ impl RemoteParticipant {
         self.inner.info.read().metadata.clone()
     }
 
+    pub fn attributes(&self) -> HashMap<String, String> {
+        self.inner.info.read().attributes.clone()
+    }
+
+    pub fn kind(&self) -> ParticipantKind {
+        self.inner.info.read().kind
+    }
+
+    /// None until the server sent the permissions of the participant
+    pub fn permissions(&self) -> Option<ParticipantPermissions> {
+        self.inner.info.read().permissions.clone()
+    }
+
     pub fn is_speaking(&self) -> bool {
         self.inner.info.read().speaking
     }
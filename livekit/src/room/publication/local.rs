@@ -1,12 +1,22 @@
 use super::TrackPublicationInner;
+use crate::options;
+use crate::options::TrackPublishOptions;
 use crate::prelude::*;
+use crate::rtc_engine::EngineError;
 use livekit_protocol as proto;
+use livekit_webrtc::stats::RtcStats;
+use parking_lot::Mutex;
 use std::fmt::Debug;
 use std::sync::Arc;
 
+struct LocalInner {
+    publish_options: Mutex<TrackPublishOptions>,
+}
+
 #[derive(Clone)]
 pub struct LocalTrackPublication {
     inner: Arc<TrackPublicationInner>,
+    local: Arc<LocalInner>,
 }
 
 impl Debug for LocalTrackPublication {
@@ -20,9 +30,16 @@ impl Debug for LocalTrackPublication {
 }
 
 impl LocalTrackPublication {
-    pub(crate) fn new(info: proto::TrackInfo, track: LocalTrack) -> Self {
+    pub(crate) fn new(
+        info: proto::TrackInfo,
+        track: LocalTrack,
+        publish_options: TrackPublishOptions,
+    ) -> Self {
         Self {
             inner: super::new_inner(info, Some(track.into())),
+            local: Arc::new(LocalInner {
+                publish_options: Mutex::new(publish_options),
+            }),
         }
     }
 
@@ -38,11 +55,88 @@ impl LocalTrackPublication {
         super::set_track(&self.inner, &TrackPublication::Local(self.clone()), track);
     }
 
-    #[allow(dead_code)]
     pub(crate) fn update_info(&self, info: proto::TrackInfo) {
         super::update_info(&self.inner, &TrackPublication::Local(self.clone()), info);
     }
 
+    /// Stats of the sender of this track (outbound-rtp, media-source, candidate-pair, ...)
+    pub async fn stats(&self) -> RoomResult<Vec<RtcStats>> {
+        let Some(transceiver) = self.track().transceiver() else {
+            return Err(RoomError::Internal("the track isn't published".to_owned()));
+        };
+
+        let stats = transceiver
+            .sender()
+            .get_stats()
+            .await
+            .map_err(EngineError::from)?;
+        Ok(stats)
+    }
+
+    /// Dynacast: only encode the simulcast layers that have subscribers
+    pub(crate) fn set_subscribed_qualities(
+        &self,
+        qualities: &[proto::SubscribedQuality],
+    ) -> RoomResult<()> {
+        let LocalTrack::Video(_) = self.track() else {
+            return Ok(());
+        };
+
+        let Some(transceiver) = self.track().transceiver() else {
+            return Err(RoomError::Internal("the track isn't published".to_owned()));
+        };
+
+        let sender = transceiver.sender();
+        let mut parameters = sender.parameters();
+        if parameters.encodings.is_empty() {
+            return Ok(());
+        }
+
+        let is_enabled = |quality: proto::VideoQuality| {
+            qualities
+                .iter()
+                .find(|subscribed| subscribed.quality == quality as i32)
+                .map(|subscribed| subscribed.enabled)
+        };
+
+        if parameters.encodings.len() == 1 {
+            // Not simulcasted, keep the track active if any quality is subscribed
+            parameters.encodings[0].active = qualities.iter().any(|quality| quality.enabled);
+        } else {
+            for encoding in &mut parameters.encodings {
+                let Some(quality) = options::video_quality_for_rid(&encoding.rid) else {
+                    continue;
+                };
+
+                if let Some(enabled) = is_enabled(quality) {
+                    encoding.active = enabled;
+                }
+            }
+        }
+
+        log::debug!(
+            "updating publishing layers of {}: {:?}",
+            self.sid(),
+            parameters
+                .encodings
+                .iter()
+                .map(|encoding| (&encoding.rid, encoding.active))
+                .collect::<Vec<_>>()
+        );
+
+        sender
+            .set_parameters(parameters)
+            .map_err(EngineError::from)?;
+        Ok(())
+    }
+
+    /// Options used when the track was published, they're reused when republishing the track
+    /// (e.g after a full reconnection)
+    pub fn publish_options(&self) -> TrackPublishOptions {
+        self.local.publish_options.lock().clone()
+    }
+
+    /// Mute the track locally and notify the SFU
     pub fn mute(&self) {
         self.track().mute();
     }
@@ -91,7 +185,7 @@ impl LocalTrackPublication {
     }
 
     pub fn is_muted(&self) -> bool {
-        self.inner.info.read().muted
+        self.track().is_muted()
     }
 
     pub fn is_remote(&self) -> bool {
@@ -25,6 +25,23 @@ pub enum PermissionStatus {
     NotAllowed,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl From<VideoQuality> for proto::VideoQuality {
+    fn from(quality: VideoQuality) -> Self {
+        match quality {
+            VideoQuality::Low => Self::Low,
+            VideoQuality::Medium => Self::Medium,
+            VideoQuality::High => Self::High,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum TrackPublication {
     Local(LocalTrackPublication),
@@ -1,9 +1,17 @@
-use super::{PermissionStatus, SubscriptionStatus, TrackPublication, TrackPublicationInner};
+use super::{
+    PermissionStatus, SubscriptionStatus, TrackPublication, TrackPublicationInner, VideoQuality,
+};
 use crate::prelude::*;
 use livekit_protocol as proto;
 use parking_lot::{Mutex, RwLock};
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+const VIDEO_HINT_DEBOUNCE: Duration = Duration::from_millis(100);
 
 #[derive(Default)]
 struct RemoteEvents {
@@ -16,17 +24,42 @@ struct RemoteEvents {
         Option<Box<dyn Fn(RemoteTrackPublication, PermissionStatus, PermissionStatus) + Send>>,
     >, // Old status, new status
     subscription_update_needed: Mutex<Option<Box<dyn Fn(RemoteTrackPublication) + Send>>>,
+    track_settings_update_needed: Mutex<Option<Box<dyn Fn(RemoteTrackPublication) + Send>>>,
+}
+
+/// Settings sent to the SFU with UpdateTrackSettings
+/// The SFU uses the dimension to select the simulcast layer when it is set, otherwise the quality
+#[derive(Debug, Clone)]
+struct TrackSettings {
+    enabled: bool,
+    quality: VideoQuality,
+    dimension: Option<TrackDimension>,
+    fps: Option<u32>,
+}
+
+impl Default for TrackSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quality: VideoQuality::High,
+            dimension: None,
+            fps: None,
+        }
+    }
 }
 
 #[derive(Debug)]
 struct RemoteInfo {
-    subscribed: bool,
+    subscribed: Option<bool>, // None if the user never changed the subscription (auto_subscribe)
     allowed: bool,
+    settings: TrackSettings,
+    settings_changed: bool, // True if the user changed the default settings
 }
 
 struct RemoteInner {
     info: RwLock<RemoteInfo>,
     events: RemoteEvents,
+    video_hint_task: Mutex<Option<JoinHandle<()>>>,
 }
 
 #[derive(Clone)]
@@ -50,10 +83,13 @@ impl RemoteTrackPublication {
             inner: super::new_inner(info, track.map(Into::into)),
             remote: Arc::new(RemoteInner {
                 info: RwLock::new(RemoteInfo {
-                    subscribed: false,
+                    subscribed: None,
                     allowed: true,
+                    settings: Default::default(),
+                    settings_changed: false,
                 }),
                 events: Default::default(),
+                video_hint_task: Default::default(),
             }),
         }
     }
@@ -68,6 +104,10 @@ impl RemoteTrackPublication {
         let prev_track = self.track();
 
         if let Some(prev_track) = prev_track {
+            if let RemoteTrack::Video(video_track) = &prev_track {
+                video_track.on_video_hint_changed(|_| {});
+            }
+
             if let Some(unsubscribed) = self.remote.events.unsubscribed.lock().as_ref() {
                 unsubscribed(self.clone(), prev_track);
             }
@@ -83,6 +123,11 @@ impl RemoteTrackPublication {
             if let Some(subscribed) = self.remote.events.subscribed.lock().as_ref() {
                 subscribed(self.clone(), track);
             }
+
+            // The SFU ignores the settings of tracks that aren't subscribed yet
+            if self.remote.info.read().settings_changed {
+                self.notify_track_settings_update();
+            }
         }
 
         self.emit_subscription_update(old_subscription_state);
@@ -160,7 +205,6 @@ impl RemoteTrackPublication {
         *self.remote.events.subscription_status_changed.lock() = Some(Box::new(f));
     }
 
-    #[allow(dead_code)]
     pub(crate) fn on_permission_status_changed(
         &self,
         f: impl Fn(RemoteTrackPublication, PermissionStatus, PermissionStatus) + Send + 'static,
@@ -175,15 +219,120 @@ impl RemoteTrackPublication {
         *self.remote.events.subscription_update_needed.lock() = Some(Box::new(f));
     }
 
+    pub(crate) fn on_track_settings_update_needed(
+        &self,
+        f: impl Fn(RemoteTrackPublication) + Send + 'static,
+    ) {
+        *self.remote.events.track_settings_update_needed.lock() = Some(Box::new(f));
+    }
+
+    fn update_settings(&self, f: impl FnOnce(&mut TrackSettings)) {
+        {
+            let mut info = self.remote.info.write();
+            f(&mut info.settings);
+            info.settings_changed = true;
+        }
+
+        self.notify_track_settings_update();
+    }
+
+    fn notify_track_settings_update(&self) {
+        if let Some(track_settings_update_needed) = self
+            .remote
+            .events
+            .track_settings_update_needed
+            .lock()
+            .as_ref()
+        {
+            track_settings_update_needed(self.clone());
+        }
+    }
+
+    /// Pause or resume the reception of the track without unsubscribing
+    pub fn set_enabled(&self, enabled: bool) {
+        self.update_settings(|settings| settings.enabled = enabled);
+    }
+
+    /// Request a specific simulcast layer, this replaces the dimensions set with
+    /// [RemoteTrackPublication::set_video_dimensions]
+    pub fn set_video_quality(&self, quality: VideoQuality) {
+        self.update_settings(|settings| {
+            settings.quality = quality;
+            settings.dimension = None;
+        });
+    }
+
+    /// Let the SFU select the simulcast layer matching the dimensions the track is rendered at
+    pub fn set_video_dimensions(&self, dimension: TrackDimension) {
+        self.update_settings(|settings| settings.dimension = Some(dimension));
+    }
+
+    pub fn set_video_fps(&self, fps: u32) {
+        self.update_settings(|settings| settings.fps = Some(fps));
+    }
+
+    /// Adaptive stream: follow the video hints of the subscribed track.
+    /// The updates are debounced, a renderer being resized would otherwise flood the SFU.
+    /// Must be called from the runtime, the hints are often set from a renderer thread
+    pub(crate) fn enable_adaptive_stream(&self, video_track: &RemoteVideoTrack) {
+        let publication = self.clone();
+        let handle = Handle::current();
+        video_track.on_video_hint_changed(move |hint| {
+            let mut video_hint_task = publication.remote.video_hint_task.lock();
+            if let Some(task) = video_hint_task.take() {
+                task.abort();
+            }
+
+            let publication = publication.clone();
+            *video_hint_task = Some(handle.spawn(async move {
+                sleep(VIDEO_HINT_DEBOUNCE).await;
+                publication.update_settings(|settings| {
+                    settings.enabled = hint.visible;
+                    settings.dimension = hint.rendered_dimension;
+                });
+            }));
+        });
+    }
+
+    /// The settings to send to the SFU, None if they were never changed
+    pub(crate) fn track_settings(&self) -> Option<proto::UpdateTrackSettings> {
+        let info = self.remote.info.read();
+        if !info.settings_changed {
+            return None;
+        }
+
+        let settings = &info.settings;
+        let mut update = proto::UpdateTrackSettings {
+            track_sids: vec![self.sid().to_string()],
+            disabled: !settings.enabled,
+            quality: proto::VideoQuality::from(settings.quality) as i32,
+            fps: settings.fps.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        if let Some(TrackDimension(width, height)) = settings.dimension {
+            update.width = width;
+            update.height = height;
+        }
+
+        Some(update)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.remote.info.read().settings.enabled
+    }
+
     pub async fn set_subscribed(&self, subscribed: bool) {
         let old_subscription_state = self.subscription_status();
         let old_permission_state = self.permission_status();
 
-        let mut info = self.remote.info.write();
-        info.subscribed = subscribed;
+        {
+            let mut info = self.remote.info.write();
+            info.subscribed = Some(subscribed);
 
-        if subscribed {
-            info.allowed = true;
+            if subscribed {
+                info.allowed = true;
+            }
         }
 
         // Request to send an update to the SFU
@@ -201,8 +350,25 @@ impl RemoteTrackPublication {
         self.emit_permission_update(old_permission_state);
     }
 
+    /// Called when the publisher changed the subscription permissions of this track
+    pub(crate) fn set_allowed(&self, allowed: bool) {
+        let old_subscription_state = self.subscription_status();
+        let old_permission_state = self.permission_status();
+
+        self.remote.info.write().allowed = allowed;
+
+        self.emit_subscription_update(old_subscription_state);
+        self.emit_permission_update(old_permission_state);
+    }
+
+    /// The subscription explicitly requested with [RemoteTrackPublication::set_subscribed]
+    /// None if the default behavior of the room is used (auto_subscribe)
+    pub(crate) fn subscription_preference(&self) -> Option<bool> {
+        self.remote.info.read().subscribed
+    }
+
     pub fn subscription_status(&self) -> SubscriptionStatus {
-        if !self.is_subscribed() {
+        if self.subscription_preference() == Some(false) {
             return SubscriptionStatus::Unsubscribed;
         }
 
@@ -222,6 +388,10 @@ impl RemoteTrackPublication {
     }
 
     pub fn is_subscribed(&self) -> bool {
+        if self.subscription_preference() == Some(false) {
+            return false;
+        }
+
         self.is_allowed() && self.track().is_some()
     }
 
@@ -0,0 +1,253 @@
+use rand::Rng;
+use std::fmt::Debug;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconnectMode {
+    /// Resume the current session (ICE restart + signal reconnection)
+    Resume,
+    /// Create a new session, the local tracks are republished
+    Restart,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconnectContext {
+    /// Index of the attempt, starting at 0
+    pub attempt: u32,
+    /// Time elapsed since the connection was lost
+    pub elapsed: Duration,
+    /// False when the server or a previous attempt requires a full reconnection
+    /// (A Resume returned by the policy is then ignored)
+    pub can_resume: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectAttempt {
+    pub mode: ReconnectMode,
+    /// Time to wait before starting this attempt
+    pub delay: Duration,
+}
+
+/// Decides how and when the engine tries to recover a lost connection
+pub trait ReconnectPolicy: Debug + Send + Sync {
+    /// Return None to stop reconnecting, the room is then disconnected
+    fn next_attempt(&self, context: &ReconnectContext) -> Option<ReconnectAttempt>;
+}
+
+fn resume_or_restart(context: &ReconnectContext, resume_attempts: u32) -> ReconnectMode {
+    if context.can_resume && context.attempt < resume_attempts {
+        ReconnectMode::Resume
+    } else {
+        ReconnectMode::Restart
+    }
+}
+
+/// Retry directly, then wait `initial_delay * multiplier^n` (capped to `max_delay`)
+/// with a random jitter to avoid every client reconnecting at the same time
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    pub max_attempts: u32,
+    /// Number of attempts trying to resume before falling back to a full reconnection
+    pub resume_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    pub max_jitter: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            resume_attempts: 3,
+            initial_delay: Duration::from_millis(300),
+            max_delay: Duration::from_secs(7),
+            multiplier: 2.0,
+            max_jitter: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl ReconnectPolicy for ExponentialBackoff {
+    fn next_attempt(&self, context: &ReconnectContext) -> Option<ReconnectAttempt> {
+        if context.attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = if context.attempt == 0 {
+            Duration::ZERO
+        } else {
+            // The factor overflows to infinity after enough attempts, the delay is then capped
+            let exponent = i32::try_from(context.attempt - 1).unwrap_or(i32::MAX);
+            let factor = self.multiplier.powi(exponent);
+            let delay = Duration::try_from_secs_f64(self.initial_delay.as_secs_f64() * factor)
+                .unwrap_or(self.max_delay)
+                .min(self.max_delay);
+            let jitter = rand::thread_rng().gen_range(0..=self.max_jitter.as_millis() as u64);
+            delay + Duration::from_millis(jitter)
+        };
+
+        Some(ReconnectAttempt {
+            mode: resume_or_restart(context, self.resume_attempts),
+            delay,
+        })
+    }
+}
+
+/// Retry directly, then every `interval`
+#[derive(Debug, Clone)]
+pub struct FixedInterval {
+    pub max_attempts: u32,
+    /// Number of attempts trying to resume before falling back to a full reconnection
+    pub resume_attempts: u32,
+    pub interval: Duration,
+}
+
+impl Default for FixedInterval {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            resume_attempts: 10,
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ReconnectPolicy for FixedInterval {
+    fn next_attempt(&self, context: &ReconnectContext) -> Option<ReconnectAttempt> {
+        if context.attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = if context.attempt == 0 {
+            Duration::ZERO
+        } else {
+            self.interval
+        };
+
+        Some(ReconnectAttempt {
+            mode: resume_or_restart(context, self.resume_attempts),
+            delay,
+        })
+    }
+}
+
+/// Disconnect the room as soon as the connection is lost
+#[derive(Debug, Clone, Default)]
+pub struct NeverReconnect;
+
+impl ReconnectPolicy for NeverReconnect {
+    fn next_attempt(&self, _: &ReconnectContext) -> Option<ReconnectAttempt> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(attempt: u32) -> ReconnectContext {
+        ReconnectContext {
+            attempt,
+            elapsed: Duration::ZERO,
+            can_resume: true,
+        }
+    }
+
+    // The delays are computed with floats, they can be off by a nanosecond
+    fn assert_delay(delay: Duration, expected: Duration) {
+        let diff = delay.max(expected) - delay.min(expected);
+        assert!(
+            diff < Duration::from_micros(1),
+            "{:?} != {:?}",
+            delay,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = ExponentialBackoff {
+            max_jitter: Duration::ZERO,
+            ..Default::default()
+        };
+
+        let first = policy.next_attempt(&context(0)).unwrap();
+        assert_eq!(first.delay, Duration::ZERO);
+        assert_eq!(first.mode, ReconnectMode::Resume);
+
+        assert_delay(
+            policy.next_attempt(&context(1)).unwrap().delay,
+            Duration::from_millis(300),
+        );
+        assert_delay(
+            policy.next_attempt(&context(3)).unwrap().delay,
+            Duration::from_millis(1200),
+        );
+        assert_eq!(
+            policy.next_attempt(&context(3)).unwrap().mode,
+            ReconnectMode::Restart
+        );
+
+        // Capped to max_delay
+        assert_eq!(
+            policy.next_attempt(&context(9)).unwrap().delay,
+            policy.max_delay
+        );
+
+        // Exhausted
+        assert!(policy.next_attempt(&context(10)).is_none());
+    }
+
+    #[test]
+    fn test_exponential_backoff_overflow() {
+        let policy = ExponentialBackoff {
+            max_attempts: u32::MAX,
+            max_jitter: Duration::ZERO,
+            ..Default::default()
+        };
+
+        for attempt in [1_100, 100_000, u32::MAX - 1] {
+            assert_eq!(
+                policy.next_attempt(&context(attempt)).unwrap().delay,
+                policy.max_delay
+            );
+        }
+    }
+
+    #[test]
+    fn test_exponential_backoff_jitter() {
+        let policy = ExponentialBackoff::default();
+        for _ in 0..100 {
+            let delay = policy.next_attempt(&context(1)).unwrap().delay;
+            assert!(delay + Duration::from_micros(1) >= policy.initial_delay);
+            assert!(delay <= policy.initial_delay + policy.max_jitter);
+        }
+    }
+
+    #[test]
+    fn test_fixed_interval() {
+        let policy = FixedInterval {
+            max_attempts: 3,
+            resume_attempts: 1,
+            interval: Duration::from_secs(2),
+        };
+
+        let first = policy.next_attempt(&context(0)).unwrap();
+        assert_eq!(first.delay, Duration::ZERO);
+        assert_eq!(first.mode, ReconnectMode::Resume);
+
+        let second = policy.next_attempt(&context(1)).unwrap();
+        assert_eq!(second.delay, Duration::from_secs(2));
+        assert_eq!(second.mode, ReconnectMode::Restart);
+
+        let restart = policy.next_attempt(&ReconnectContext {
+            can_resume: false,
+            ..context(0)
+        });
+        assert_eq!(restart.unwrap().mode, ReconnectMode::Restart);
+
+        assert!(policy.next_attempt(&context(3)).is_none());
+        assert!(NeverReconnect.next_attempt(&context(0)).is_none());
+    }
+}
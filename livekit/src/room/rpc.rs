@@ -0,0 +1,506 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tokio::time::{sleep, sleep_until, Instant};
+
+/// Topic of the packets used by the RPC requests, acks and responses
+pub(crate) const RPC_TOPIC: &str = "lk.rpc";
+
+/// Max size of the payload of a request or a response
+pub const MAX_RPC_PAYLOAD_BYTES: usize = 15_360;
+
+/// Time given to the recipient to acknowledge a request before failing with ConnectionTimeout
+pub(crate) const RPC_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const DEFAULT_RPC_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u32)]
+pub enum RpcErrorCode {
+    ApplicationError = 1500,
+    ConnectionTimeout = 1501,
+    ResponseTimeout = 1502,
+    RecipientDisconnected = 1503,
+    ResponsePayloadTooLarge = 1504,
+    SendFailed = 1505,
+
+    UnsupportedMethod = 1400,
+    RecipientNotFound = 1401,
+    RequestPayloadTooLarge = 1402,
+}
+
+impl RpcErrorCode {
+    fn message(self) -> &'static str {
+        match self {
+            Self::ApplicationError => "Application error in method handler",
+            Self::ConnectionTimeout => "Connection timeout",
+            Self::ResponseTimeout => "Response timeout",
+            Self::RecipientDisconnected => "Recipient disconnected",
+            Self::ResponsePayloadTooLarge => "Response payload too large",
+            Self::SendFailed => "Failed to send",
+            Self::UnsupportedMethod => "Method not supported at destination",
+            Self::RecipientNotFound => "Recipient not found",
+            Self::RequestPayloadTooLarge => "Request payload too large",
+        }
+    }
+}
+
+/// Error returned by [LocalParticipant::perform_rpc].
+/// Method handlers can return their own errors, codes below 1000 are free for the application
+#[derive(Error, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[error("rpc error {code}: {message}")]
+pub struct RpcError {
+    pub code: u32,
+    pub message: String,
+    pub data: Option<String>,
+}
+
+impl RpcError {
+    pub fn new(code: u32, message: String, data: Option<String>) -> Self {
+        Self {
+            code,
+            message,
+            data,
+        }
+    }
+
+    pub fn built_in(code: RpcErrorCode, data: Option<String>) -> Self {
+        Self::new(code as u32, code.message().to_owned(), data)
+    }
+}
+
+/// Data passed to the method handlers registered with [LocalParticipant::register_rpc_method]
+#[derive(Debug, Clone)]
+pub struct RpcInvocationData {
+    pub request_id: String,
+    pub caller_identity: ParticipantIdentity,
+    pub payload: String,
+    pub response_timeout: Duration,
+}
+
+pub(crate) type RpcHandler = Arc<
+    dyn Fn(RpcInvocationData) -> Pin<Box<dyn Future<Output = Result<String, RpcError>> + Send>>
+        + Send
+        + Sync,
+>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum RpcMessage {
+    Request {
+        id: String,
+        method: String,
+        payload: String,
+        response_timeout_ms: u64,
+    },
+    Ack {
+        request_id: String,
+    },
+    Response {
+        request_id: String,
+        payload: Option<String>,
+        error: Option<RpcError>,
+    },
+}
+
+/// A request sent with perform_rpc, waiting for its ack and its response
+pub(crate) struct PendingRequest {
+    pub destination_identity: ParticipantIdentity,
+    pub ack_tx: Option<oneshot::Sender<()>>,
+    pub response_tx: oneshot::Sender<Result<String, RpcError>>,
+}
+
+/// Returned when a request is started, used to wait for its ack and its response
+pub(crate) struct RequestHandle {
+    pub destination_sid: ParticipantSid,
+    pub ack_rx: oneshot::Receiver<()>,
+    pub response_rx: oneshot::Receiver<Result<String, RpcError>>,
+}
+
+#[derive(Default)]
+pub(crate) struct RpcState {
+    pub handlers: HashMap<String, RpcHandler>,
+    pub pending_requests: HashMap<String, PendingRequest>,
+    /// Used to address the requests, the packets are routed using the participant sids
+    pub participants: HashMap<ParticipantIdentity, ParticipantSid>,
+}
+
+impl RpcState {
+    pub fn start_request(
+        &mut self,
+        request_id: String,
+        destination_identity: ParticipantIdentity,
+    ) -> Result<RequestHandle, RpcError> {
+        let Some(destination_sid) = self.participants.get(&destination_identity).cloned() else {
+            return Err(RpcError::built_in(RpcErrorCode::RecipientNotFound, None));
+        };
+
+        // Forget the requests whose caller stopped waiting
+        self.pending_requests
+            .retain(|_, pending| !pending.response_tx.is_closed());
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        let (response_tx, response_rx) = oneshot::channel();
+        self.pending_requests.insert(
+            request_id,
+            PendingRequest {
+                destination_identity,
+                ack_tx: Some(ack_tx),
+                response_tx,
+            },
+        );
+
+        Ok(RequestHandle {
+            destination_sid,
+            ack_rx,
+            response_rx,
+        })
+    }
+
+    pub fn finish_request(&mut self, request_id: &str) {
+        self.pending_requests.remove(request_id);
+    }
+
+    pub fn handle_ack(&mut self, request_id: &str) {
+        if let Some(pending) = self.pending_requests.get_mut(request_id) {
+            if let Some(ack_tx) = pending.ack_tx.take() {
+                let _ = ack_tx.send(());
+            }
+        }
+    }
+
+    /// Returns false if the request is unknown (e.g. it already timed out)
+    pub fn handle_response(&mut self, request_id: &str, res: Result<String, RpcError>) -> bool {
+        let Some(pending) = self.pending_requests.remove(request_id) else {
+            return false;
+        };
+
+        let _ = pending.response_tx.send(res);
+        true
+    }
+
+    pub fn add_participant(&mut self, identity: ParticipantIdentity, sid: ParticipantSid) {
+        self.participants.insert(identity, sid);
+    }
+
+    /// Fail the pending requests sent to a participant that left the room
+    pub fn remove_participant(&mut self, identity: &ParticipantIdentity, sid: &ParticipantSid) {
+        // The identity may already be used by a new participant (e.g. it rejoined with a new sid)
+        if self.participants.get(identity) != Some(sid) {
+            return;
+        }
+
+        self.participants.remove(identity);
+        let (disconnected, pending_requests): (HashMap<_, _>, HashMap<_, _>) =
+            std::mem::take(&mut self.pending_requests)
+                .into_iter()
+                .partition(|(_, pending)| &pending.destination_identity == identity);
+        self.pending_requests = pending_requests;
+
+        for (_, pending) in disconnected {
+            let _ = pending.response_tx.send(Err(RpcError::built_in(
+                RpcErrorCode::RecipientDisconnected,
+                None,
+            )));
+        }
+    }
+}
+
+/// Wait for the ack of a request, then for its response
+pub(crate) async fn wait_response(
+    handle: RequestHandle,
+    ack_timeout: Duration,
+    response_timeout: Duration,
+) -> Result<String, RpcError> {
+    let RequestHandle {
+        mut ack_rx,
+        mut response_rx,
+        ..
+    } = handle;
+
+    let deadline = Instant::now() + response_timeout;
+    let recipient_disconnected = |_| {
+        Err(RpcError::built_in(
+            RpcErrorCode::RecipientDisconnected,
+            None,
+        ))
+    };
+
+    tokio::select! {
+        biased;
+        res = &mut response_rx => return res.unwrap_or_else(recipient_disconnected),
+        Ok(_) = &mut ack_rx => {},
+        _ = sleep(ack_timeout) => {
+            return Err(RpcError::built_in(RpcErrorCode::ConnectionTimeout, None));
+        },
+        _ = sleep_until(deadline) => {
+            return Err(RpcError::built_in(RpcErrorCode::ResponseTimeout, None));
+        },
+    }
+
+    tokio::select! {
+        res = response_rx => res.unwrap_or_else(recipient_disconnected),
+        _ = sleep_until(deadline) => {
+            Err(RpcError::built_in(RpcErrorCode::ResponseTimeout, None))
+        },
+    }
+}
+
+/// Run the handler of an incoming request and returns the result to send back
+pub(crate) async fn run_handler(
+    handler: Option<RpcHandler>,
+    data: RpcInvocationData,
+) -> Result<String, RpcError> {
+    if data.payload.len() > MAX_RPC_PAYLOAD_BYTES {
+        return Err(RpcError::built_in(
+            RpcErrorCode::RequestPayloadTooLarge,
+            None,
+        ));
+    }
+
+    let Some(handler) = handler else {
+        return Err(RpcError::built_in(RpcErrorCode::UnsupportedMethod, None));
+    };
+
+    // Run the handler in its own task so a panic is reported to the caller
+    let payload = tokio::spawn(handler(data))
+        .await
+        .unwrap_or_else(|_| Err(RpcError::built_in(RpcErrorCode::ApplicationError, None)))?;
+
+    if payload.len() > MAX_RPC_PAYLOAD_BYTES {
+        return Err(RpcError::built_in(
+            RpcErrorCode::ResponsePayloadTooLarge,
+            None,
+        ));
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(identity: &str) -> ParticipantIdentity {
+        identity.to_owned().into()
+    }
+
+    fn sid(sid: &str) -> ParticipantSid {
+        sid.to_owned().into()
+    }
+
+    fn invocation_data(payload: &str) -> RpcInvocationData {
+        RpcInvocationData {
+            request_id: "req".to_owned(),
+            caller_identity: identity("alice"),
+            payload: payload.to_owned(),
+            response_timeout: DEFAULT_RPC_RESPONSE_TIMEOUT,
+        }
+    }
+
+    fn handler(
+        f: impl Fn(RpcInvocationData) -> Result<String, RpcError> + Send + Sync + 'static,
+    ) -> RpcHandler {
+        let f = Arc::new(f);
+        Arc::new(move |data| {
+            let f = f.clone();
+            Box::pin(async move { f(data) })
+        })
+    }
+
+    fn code(res: Result<String, RpcError>) -> u32 {
+        res.unwrap_err().code
+    }
+
+    #[test]
+    fn test_message_serde() {
+        let request = RpcMessage::Request {
+            id: "req".to_owned(),
+            method: "greet".to_owned(),
+            payload: "hi".to_owned(),
+            response_timeout_ms: 10_000,
+        };
+        let json: serde_json::Value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "request",
+                "id": "req",
+                "method": "greet",
+                "payload": "hi",
+                "response_timeout_ms": 10_000,
+            })
+        );
+
+        let ack: RpcMessage = serde_json::from_str(r#"{"type":"ack","request_id":"req"}"#).unwrap();
+        assert!(matches!(ack, RpcMessage::Ack { request_id } if request_id == "req"));
+
+        let response = RpcMessage::Response {
+            request_id: "req".to_owned(),
+            payload: None,
+            error: Some(RpcError::built_in(RpcErrorCode::UnsupportedMethod, None)),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        let RpcMessage::Response { error, .. } = serde_json::from_str(&json).unwrap() else {
+            panic!("expected a response");
+        };
+        assert_eq!(error.unwrap().code, RpcErrorCode::UnsupportedMethod as u32);
+
+        assert!(serde_json::from_str::<RpcMessage>(r#"{"type":"unknown"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_ack_response() {
+        let mut state = RpcState::default();
+        assert!(matches!(
+            state.start_request("req".to_owned(), identity("bob")),
+            Err(err) if err.code == RpcErrorCode::RecipientNotFound as u32
+        ));
+
+        state.add_participant(identity("bob"), sid("PA_1"));
+        let mut handle = state
+            .start_request("req".to_owned(), identity("bob"))
+            .unwrap();
+        assert_eq!(handle.destination_sid, sid("PA_1"));
+
+        // Acks and responses of other requests are ignored
+        state.handle_ack("other");
+        assert!(!state.handle_response("other", Ok(String::new())));
+        assert!(handle.ack_rx.try_recv().is_err());
+
+        state.handle_ack("req");
+        assert!(state.handle_response("req", Ok("pong".to_owned())));
+        assert!(state.pending_requests.is_empty());
+
+        let res = wait_response(handle, RPC_ACK_TIMEOUT, DEFAULT_RPC_RESPONSE_TIMEOUT).await;
+        assert_eq!(res.unwrap(), "pong");
+    }
+
+    #[tokio::test]
+    async fn test_response_before_ack() {
+        let mut state = RpcState::default();
+        state.add_participant(identity("bob"), sid("PA_1"));
+        let handle = state
+            .start_request("req".to_owned(), identity("bob"))
+            .unwrap();
+
+        state.handle_response("req", Ok("pong".to_owned()));
+        let res = wait_response(handle, RPC_ACK_TIMEOUT, DEFAULT_RPC_RESPONSE_TIMEOUT).await;
+        assert_eq!(res.unwrap(), "pong");
+    }
+
+    #[tokio::test]
+    async fn test_timeouts() {
+        let mut state = RpcState::default();
+        state.add_participant(identity("bob"), sid("PA_1"));
+
+        // Never acknowledged
+        let handle = state
+            .start_request("req1".to_owned(), identity("bob"))
+            .unwrap();
+        let res = wait_response(
+            handle,
+            Duration::from_millis(20),
+            DEFAULT_RPC_RESPONSE_TIMEOUT,
+        )
+        .await;
+        assert_eq!(code(res), RpcErrorCode::ConnectionTimeout as u32);
+
+        // Acknowledged but never answered
+        let handle = state
+            .start_request("req2".to_owned(), identity("bob"))
+            .unwrap();
+        state.handle_ack("req2");
+        let res = wait_response(handle, RPC_ACK_TIMEOUT, Duration::from_millis(20)).await;
+        assert_eq!(code(res), RpcErrorCode::ResponseTimeout as u32);
+
+        // The timed out requests are forgotten when the next one starts
+        state.finish_request("req2");
+        let _handle = state
+            .start_request("req3".to_owned(), identity("bob"))
+            .unwrap();
+        assert_eq!(state.pending_requests.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recipient_disconnected() {
+        let mut state = RpcState::default();
+        state.add_participant(identity("bob"), sid("PA_1"));
+        state.add_participant(identity("carol"), sid("PA_2"));
+
+        let bob_request = state
+            .start_request("req1".to_owned(), identity("bob"))
+            .unwrap();
+        let carol_request = state
+            .start_request("req2".to_owned(), identity("carol"))
+            .unwrap();
+
+        state.remove_participant(&identity("bob"), &sid("PA_1"));
+        assert!(!state.participants.contains_key(&identity("bob")));
+        assert_eq!(state.pending_requests.len(), 1);
+
+        let res = wait_response(bob_request, RPC_ACK_TIMEOUT, DEFAULT_RPC_RESPONSE_TIMEOUT).await;
+        assert_eq!(code(res), RpcErrorCode::RecipientDisconnected as u32);
+
+        state.handle_response("req2", Ok("pong".to_owned()));
+        let res = wait_response(carol_request, RPC_ACK_TIMEOUT, DEFAULT_RPC_RESPONSE_TIMEOUT).await;
+        assert_eq!(res.unwrap(), "pong");
+    }
+
+    #[test]
+    fn test_participant_rejoined() {
+        // The new sid of a participant can be received before the disconnection of the old one
+        // (e.g. it reconnected, or after a full reconnect of the local participant)
+        let mut state = RpcState::default();
+        state.add_participant(identity("bob"), sid("PA_1"));
+        state.add_participant(identity("bob"), sid("PA_2"));
+        let _handle = state
+            .start_request("req".to_owned(), identity("bob"))
+            .unwrap();
+
+        state.remove_participant(&identity("bob"), &sid("PA_1"));
+        assert_eq!(state.participants.get(&identity("bob")), Some(&sid("PA_2")));
+        assert_eq!(state.pending_requests.len(), 1);
+
+        let handle = state
+            .start_request("req2".to_owned(), identity("bob"))
+            .unwrap();
+        assert_eq!(handle.destination_sid, sid("PA_2"));
+    }
+
+    #[tokio::test]
+    async fn test_run_handler() {
+        let echo = handler(|data| Ok(data.payload));
+        assert_eq!(
+            run_handler(Some(echo.clone()), invocation_data("hi"))
+                .await
+                .unwrap(),
+            "hi"
+        );
+
+        let res = run_handler(None, invocation_data("hi")).await;
+        assert_eq!(code(res), RpcErrorCode::UnsupportedMethod as u32);
+
+        let large_payload = "a".repeat(MAX_RPC_PAYLOAD_BYTES + 1);
+        let res = run_handler(Some(echo), invocation_data(&large_payload)).await;
+        assert_eq!(code(res), RpcErrorCode::RequestPayloadTooLarge as u32);
+
+        let large_response = handler(|_| Ok("a".repeat(MAX_RPC_PAYLOAD_BYTES + 1)));
+        let res = run_handler(Some(large_response), invocation_data("hi")).await;
+        assert_eq!(code(res), RpcErrorCode::ResponsePayloadTooLarge as u32);
+
+        let failing = handler(|_| Err(RpcError::new(42, "failed".to_owned(), None)));
+        let res = run_handler(Some(failing), invocation_data("hi")).await;
+        assert_eq!(code(res), 42);
+
+        let panicking = handler(|_| panic!("handler panicked"));
+        let res = run_handler(Some(panicking), invocation_data("hi")).await;
+        assert_eq!(code(res), RpcErrorCode::ApplicationError as u32);
+    }
+}
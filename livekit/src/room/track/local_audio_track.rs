@@ -91,6 +91,10 @@ impl LocalAudioTrack {
         super::set_muted(&self.inner, &Track::LocalAudio(self.clone()), false);
     }
 
+    pub(crate) fn update_muted(&self, muted: bool) -> bool {
+        super::update_muted(&self.inner, muted)
+    }
+
     pub fn rtc_track(&self) -> RtcAudioTrack {
         if let MediaStreamTrack::Audio(audio) = self.inner.rtc_track.clone() {
             return audio;
@@ -17,6 +17,7 @@ impl LocalTrack {
        [Audio, Video];
         pub fn mute(self: &Self) -> ();
         pub fn unmute(self: &Self) -> ();
+        pub(crate) fn update_muted(self: &Self, muted: bool) -> bool;
     );
 
     pub fn rtc_track(&self) -> MediaStreamTrack {
@@ -91,6 +91,10 @@ impl LocalVideoTrack {
         super::set_muted(&self.inner, &Track::LocalVideo(self.clone()), false);
     }
 
+    pub(crate) fn update_muted(&self, muted: bool) -> bool {
+        super::update_muted(&self.inner, muted)
+    }
+
     pub fn rtc_track(&self) -> RtcVideoTrack {
         if let MediaStreamTrack::Video(video) = self.inner.rtc_track.clone() {
             return video;
@@ -146,10 +146,27 @@ pub(super) fn new_inner(
 }
 
 pub(super) fn set_muted(inner: &Arc<TrackInner>, track: &Track, muted: bool) {
+    if !update_muted(inner, muted) {
+        return;
+    }
+
+    if muted {
+        if let Some(on_mute) = inner.events.muted.lock().as_ref() {
+            on_mute(track.clone());
+        }
+    } else {
+        if let Some(on_unmute) = inner.events.unmuted.lock().as_ref() {
+            on_unmute(track.clone());
+        }
+    }
+}
+
+/// Update the mute state without firing the mute events, returns false if it didn't change
+pub(super) fn update_muted(inner: &Arc<TrackInner>, muted: bool) -> bool {
     let info = inner.info.read();
     log::debug!("set_muted: {} {}", info.sid, muted);
     if info.muted == muted {
-        return;
+        return false;
     }
     drop(info);
 
@@ -160,16 +177,7 @@ pub(super) fn set_muted(inner: &Arc<TrackInner>, track: &Track, muted: bool) {
     }
 
     inner.info.write().muted = muted;
-
-    if muted {
-        if let Some(on_mute) = inner.events.muted.lock().as_ref() {
-            on_mute(track.clone());
-        }
-    } else {
-        if let Some(on_unmute) = inner.events.unmuted.lock().as_ref() {
-            on_unmute(track.clone());
-        }
-    }
+    true
 }
 
 pub(super) fn update_info(inner: &Arc<TrackInner>, _track: &Track, new_info: proto::TrackInfo) {
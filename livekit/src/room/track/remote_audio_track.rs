@@ -83,12 +83,10 @@ impl RemoteAudioTrack {
         *self.inner.events.unmuted.lock() = Some(Box::new(f));
     }
 
-    #[allow(dead_code)]
     pub(crate) fn transceiver(&self) -> Option<RtpTransceiver> {
         self.inner.info.read().transceiver.clone()
     }
 
-    #[allow(dead_code)]
     pub(crate) fn set_transceiver(&self, transceiver: Option<RtpTransceiver>) {
         self.inner.info.write().transceiver = transceiver;
     }
@@ -1,6 +1,7 @@
 use super::track_dispatch;
 use super::TrackInner;
 use crate::prelude::*;
+use crate::rtc_engine::EngineError;
 use livekit_protocol as proto;
 use livekit_protocol::enum_dispatch;
 use livekit_webrtc::prelude::*;
@@ -22,6 +23,20 @@ impl RemoteTrack {
             Self::Video(track) => track.rtc_track().into(),
         }
     }
+
+    /// Stats of the receiver of this track (inbound-rtp, codec, candidate-pair, ...)
+    pub async fn stats(&self) -> RoomResult<Vec<RtcStats>> {
+        let Some(transceiver) = self.transceiver() else {
+            return Err(RoomError::Internal("the track isn't subscribed".to_owned()));
+        };
+
+        let stats = transceiver
+            .receiver()
+            .get_stats()
+            .await
+            .map_err(EngineError::from)?;
+        Ok(stats)
+    }
 }
 
 pub(super) fn update_info(inner: &Arc<TrackInner>, track: &Track, new_info: proto::TrackInfo) {
@@ -3,12 +3,39 @@ use super::TrackInner;
 use crate::prelude::*;
 use livekit_protocol as proto;
 use livekit_webrtc::prelude::*;
+use parking_lot::Mutex;
 use std::fmt::Debug;
 use std::sync::Arc;
 
+/// How the track is being consumed by the application.
+/// With adaptive stream, the SFU pauses hidden tracks and sends the simulcast layer
+/// closest to the rendered dimension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoHint {
+    pub visible: bool,
+    /// None if the dimension is unknown, the highest quality is then used
+    pub rendered_dimension: Option<TrackDimension>,
+}
+
+impl Default for VideoHint {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            rendered_dimension: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct VideoHintState {
+    hint: Mutex<VideoHint>,
+    hint_changed: Mutex<Option<Box<dyn Fn(VideoHint) + Send>>>,
+}
+
 #[derive(Clone)]
 pub struct RemoteVideoTrack {
     inner: Arc<TrackInner>,
+    video_hint: Arc<VideoHintState>,
 }
 
 impl Debug for RemoteVideoTrack {
@@ -30,6 +57,7 @@ impl RemoteVideoTrack {
                 TrackKind::Video,
                 MediaStreamTrack::Video(rtc_track),
             )),
+            video_hint: Default::default(),
         }
     }
 
@@ -84,12 +112,44 @@ impl RemoteVideoTrack {
         *self.inner.events.unmuted.lock() = Some(Box::new(f));
     }
 
-    #[allow(dead_code)]
+    pub fn video_hint(&self) -> VideoHint {
+        *self.video_hint.hint.lock()
+    }
+
+    /// Declare if the track is currently displayed (e.g. the sink is attached to a visible view)
+    pub fn set_visible(&self, visible: bool) {
+        self.update_video_hint(|hint| hint.visible = visible);
+    }
+
+    /// Declare the size the track is rendered at
+    pub fn set_rendered_dimension(&self, dimension: TrackDimension) {
+        self.update_video_hint(|hint| hint.rendered_dimension = Some(dimension));
+    }
+
+    fn update_video_hint(&self, f: impl FnOnce(&mut VideoHint)) {
+        let hint = {
+            let mut hint = self.video_hint.hint.lock();
+            let old_hint = *hint;
+            f(&mut hint);
+            if old_hint == *hint {
+                return;
+            }
+            *hint
+        };
+
+        if let Some(hint_changed) = self.video_hint.hint_changed.lock().as_ref() {
+            hint_changed(hint);
+        }
+    }
+
+    pub(crate) fn on_video_hint_changed(&self, f: impl Fn(VideoHint) + Send + 'static) {
+        *self.video_hint.hint_changed.lock() = Some(Box::new(f));
+    }
+
     pub(crate) fn transceiver(&self) -> Option<RtpTransceiver> {
         self.inner.info.read().transceiver.clone()
     }
 
-    #[allow(dead_code)]
     pub(crate) fn set_transceiver(&self, transceiver: Option<RtpTransceiver>) {
         self.inner.info.write().transceiver = transceiver;
     }
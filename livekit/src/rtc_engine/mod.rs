@@ -1,8 +1,9 @@
 use crate::options::TrackPublishOptions;
 use crate::prelude::LocalTrack;
+use crate::reconnect::{ReconnectContext, ReconnectMode, ReconnectPolicy};
 use crate::rtc_engine::lk_runtime::LkRuntime;
 use crate::rtc_engine::rtc_session::{RtcSession, SessionEvent, SessionEvents};
-use crate::DataPacketKind;
+use crate::{DataPacketKind, DisconnectReason};
 use livekit_api::signal_client::{SignalError, SignalOptions};
 use livekit_protocol as proto;
 use livekit_webrtc::prelude::*;
@@ -13,11 +14,12 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::Notify;
 use tokio::sync::RwLock as AsyncRwLock;
 use tokio::sync::{mpsc, oneshot};
-use tokio::sync::{Mutex as AsyncMutex, Notify};
 use tokio::task::JoinHandle;
-use tokio::time::{interval, Interval, MissedTickBehavior};
+use tokio::time::{sleep, Instant};
+use tokio_util::sync::CancellationToken;
 
 pub mod lk_runtime;
 mod peer_transport;
@@ -56,10 +58,38 @@ pub enum EngineError {
     Connection(String),
     #[error("decode error")]
     Decode(#[from] prost::DecodeError),
+    #[error("connection timed out during the {0:?} phase")]
+    Timeout(ConnectPhase),
+    #[error("connection cancelled")]
+    Cancelled,
     #[error("internal error: {0}")]
     Internal(String), // Unexpected error
 }
 
+/// Phases of a connection, used to report which one timed out
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConnectPhase {
+    /// Websocket handshake with the server
+    Signal,
+    /// Waiting for the JoinResponse
+    Join,
+    /// ICE connection of the PeerConnections
+    Ice,
+    /// Connection of the publisher PeerConnection (e.g. before sending data)
+    Publisher,
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineOptions {
+    /// Configuration provided by the user, merged with the JoinResponse
+    pub rtc_config: RtcConfiguration,
+    pub ice_timeout: Duration,
+    pub publisher_timeout: Duration,
+    pub reconnect_policy: Arc<dyn ReconnectPolicy>,
+    /// Only used by the initial connection
+    pub cancellation_token: Option<CancellationToken>,
+}
+
 #[derive(Debug)]
 pub enum EngineEvent {
     ParticipantUpdate {
@@ -69,10 +99,12 @@ pub enum EngineEvent {
         track: MediaStreamTrack,
         stream: MediaStream,
         receiver: RtpReceiver,
+        transceiver: RtpTransceiver,
     },
     Data {
         participant_sid: String,
         payload: Vec<u8>,
+        topic: Option<String>,
         kind: DataPacketKind,
     },
     SpeakersChanged {
@@ -81,16 +113,39 @@ pub enum EngineEvent {
     ConnectionQuality {
         updates: Vec<proto::ConnectionQualityInfo>,
     },
+    TokenRefreshed {
+        token: String,
+    },
+    RoomUpdate {
+        room: proto::Room,
+    },
+    SubscriptionPermissionUpdate {
+        update: proto::SubscriptionPermissionUpdate,
+    },
+    SubscribedQualityUpdate {
+        update: proto::SubscribedQualityUpdate,
+    },
+    LocalTrackMuted {
+        sid: String,
+        muted: bool,
+    },
+    LocalTrackUnpublished {
+        sid: String,
+    },
+    ReconnectAttempt {
+        attempt: u32,
+        mode: ReconnectMode,
+        delay: Duration,
+    },
     Resuming,
     Resumed,
     Restarting,
     Restarted,
-    Disconnected,
+    Disconnected {
+        reason: DisconnectReason,
+    },
 }
 
-pub const RECONNECT_ATTEMPTS: u32 = 10;
-pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
-
 /// Represents a running RTCSession with the ability to close the session
 /// and the engine_task
 #[derive(Debug)]
@@ -111,13 +166,16 @@ struct EngineInner {
     // (directly accessing the running_handle requires an async context to lock the Mutex and a getter needs a short lock)
     // Maybe there is a better way to do it?
     join_response: Mutex<proto::JoinResponse>,
+    // Region of the current session (LiveKit Cloud may fall back to another region when connecting)
+    region: Mutex<Option<String>>,
     running_handle: AsyncRwLock<Option<EngineHandle>>,
 
     // Reconnecting fields
     closed: AtomicBool, // True if closed or the reconnection failed (Note that this is false when reconnecting or resuming)
     reconnecting: AtomicBool,
     full_reconnect: AtomicBool, // If true, the next reconnect attempt will skip resume and directly try a full reconnect
-    reconnect_interval: AsyncMutex<Interval>,
+    options: EngineOptions,
+    retry_now: Notify,               // Skip the delay of the next attempt
     reconnect_notifier: Arc<Notify>, // Called when the reconnection task finisehd, successful or not
 }
 
@@ -140,31 +198,51 @@ impl RtcEngine {
     pub async fn connect(
         url: &str,
         token: &str,
-        options: SignalOptions,
+        signal_options: SignalOptions,
+        options: EngineOptions,
     ) -> EngineResult<(Self, EngineEvents)> {
         let (engine_emitter, engine_events) = mpsc::channel(8);
 
-        let mut reconnect_interval = interval(RECONNECT_INTERVAL);
-        reconnect_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
-
         let inner = Arc::new(EngineInner {
             lk_runtime: LkRuntime::instance(),
             running_handle: Default::default(),
             engine_emitter,
             join_response: Default::default(), // Will directly be replaced by the connect method below
+            region: Default::default(),
             closed: Default::default(),
             reconnecting: Default::default(),
             full_reconnect: Default::default(),
-            reconnect_interval: AsyncMutex::new(reconnect_interval),
+            options: options.clone(),
+            retry_now: Notify::new(),
             reconnect_notifier: Arc::new(Notify::new()),
         });
 
-        inner.connect(url, token, options).await?;
+        let connect = async {
+            inner.connect(url, token, signal_options).await?;
+
+            let handle = inner.running_handle.read().await;
+            let session = &handle.as_ref().unwrap().session;
+            session.wait_pc_connection().await
+        };
+
+        let res = match options.cancellation_token {
+            Some(cancellation_token) => tokio::select! {
+                res = connect => res,
+                _ = cancellation_token.cancelled() => Err(EngineError::Cancelled),
+            },
+            None => connect.await,
+        };
+
+        if let Err(err) = res {
+            inner.terminate_session().await;
+            return Err(err);
+        }
+
         Ok((Self { inner }, engine_events))
     }
 
     pub async fn close(&self) {
-        self.inner.close().await
+        self.inner.close(DisconnectReason::ClientInitiated).await
     }
 
     pub async fn publish_data(
@@ -183,8 +261,7 @@ impl RtcEngine {
         self.inner.wait_reconnection().await?;
         let handle = self.inner.running_handle.read().await;
         let session = &handle.as_ref().unwrap().session;
-        session.simulate_scenario(scenario).await;
-        Ok(())
+        session.simulate_scenario(scenario).await
     }
 
     pub async fn add_track(&self, req: proto::AddTrackRequest) -> EngineResult<proto::TrackInfo> {
@@ -221,21 +298,45 @@ impl RtcEngine {
         session.negotiate_publisher().await
     }
 
+    pub async fn send_sync_state(
+        &self,
+        subscription: proto::UpdateSubscription,
+        publish_tracks: Vec<proto::TrackPublishedResponse>,
+    ) -> EngineResult<()> {
+        self.inner.wait_reconnection().await?;
+        let handle = self.inner.running_handle.read().await;
+        let session = &handle.as_ref().unwrap().session;
+        session.sync_state(subscription, publish_tracks).await
+    }
+
+    /// Send a request to the server, the request is queued by the SignalClient when resuming.
+    /// During a full reconnect, the request is discarded and an error is returned
     pub async fn send_request(&self, msg: proto::signal_request::Message) -> EngineResult<()> {
-        if self.inner.reconnecting.load(Ordering::Acquire) {
-            // When doing a full reconnect, it is safe to ignore the messages, we don't wait for reconnection here
-            return Ok(()); // TODO(theomonnom): Maybe we should still return an error instead?
+        if self.inner.reconnecting.load(Ordering::Acquire)
+            && self.inner.full_reconnect.load(Ordering::Acquire)
+        {
+            // The request is meaningless for the new session, the room resyncs its state once restarted
+            Err(EngineError::Connection(
+                "request discarded, the engine is restarting".to_owned(),
+            ))?
         }
 
         let handle = self.inner.running_handle.read().await;
-        let session = &handle.as_ref().unwrap().session; // Unwrap should be OK here (running_handle is always valid when not reconnecting)
-        session.signal_client().send(msg).await;
+        let Some(handle) = handle.as_ref() else {
+            Err(EngineError::Connection("engine is closed".to_owned()))?
+        };
+
+        handle.session.signal_client().send(msg).await?;
         Ok(())
     }
 
     pub fn join_response(&self) -> proto::JoinResponse {
         self.inner.join_response.lock().clone()
     }
+
+    pub fn region(&self) -> Option<String> {
+        self.inner.region.lock().clone()
+    }
 }
 
 impl EngineInner {
@@ -279,7 +380,7 @@ impl EngineInner {
                     tokio::spawn({
                         let inner = self.clone();
                         async move {
-                            inner.close().await;
+                            inner.close(reason.into()).await;
                         }
                     });
                 }
@@ -287,6 +388,7 @@ impl EngineInner {
             SessionEvent::Data {
                 participant_sid,
                 payload,
+                topic,
                 kind,
             } => {
                 let _ = self
@@ -294,6 +396,7 @@ impl EngineInner {
                     .send(EngineEvent::Data {
                         participant_sid,
                         payload,
+                        topic,
                         kind,
                     })
                     .await;
@@ -302,6 +405,7 @@ impl EngineInner {
                 track,
                 stream,
                 receiver,
+                transceiver,
             } => {
                 let _ = self
                     .engine_emitter
@@ -309,6 +413,7 @@ impl EngineInner {
                         track,
                         stream,
                         receiver,
+                        transceiver,
                     })
                     .await;
             }
@@ -330,6 +435,42 @@ impl EngineInner {
                     .send(EngineEvent::ConnectionQuality { updates })
                     .await;
             }
+            SessionEvent::RefreshToken { token } => {
+                let _ = self
+                    .engine_emitter
+                    .send(EngineEvent::TokenRefreshed { token })
+                    .await;
+            }
+            SessionEvent::RoomUpdate { room } => {
+                let _ = self
+                    .engine_emitter
+                    .send(EngineEvent::RoomUpdate { room })
+                    .await;
+            }
+            SessionEvent::SubscriptionPermissionUpdate { update } => {
+                let _ = self
+                    .engine_emitter
+                    .send(EngineEvent::SubscriptionPermissionUpdate { update })
+                    .await;
+            }
+            SessionEvent::SubscribedQualityUpdate { update } => {
+                let _ = self
+                    .engine_emitter
+                    .send(EngineEvent::SubscribedQualityUpdate { update })
+                    .await;
+            }
+            SessionEvent::LocalTrackMuted { sid, muted } => {
+                let _ = self
+                    .engine_emitter
+                    .send(EngineEvent::LocalTrackMuted { sid, muted })
+                    .await;
+            }
+            SessionEvent::LocalTrackUnpublished { sid } => {
+                let _ = self
+                    .engine_emitter
+                    .send(EngineEvent::LocalTrackUnpublished { sid })
+                    .await;
+            }
             SessionEvent::Connected => {}
         }
         Ok(())
@@ -344,7 +485,7 @@ impl EngineInner {
         let mut running_handle = self.running_handle.write().await;
 
         let (session, join_response, session_events) =
-            RtcSession::connect(url, token, options).await?;
+            RtcSession::connect(url, token, options, &self.options).await?;
 
         let (close_sender, close_receiver) = oneshot::channel();
         let engine_task = tokio::spawn(self.clone().engine_task(session_events, close_receiver));
@@ -357,6 +498,7 @@ impl EngineInner {
 
         // Always update the join response after a new session is created (first session or full reconnect)
         *self.join_response.lock() = join_response;
+        *self.region.lock() = engine_handle.session.signal_client().region();
         *running_handle = Some(engine_handle);
         Ok(())
     }
@@ -369,10 +511,13 @@ impl EngineInner {
         }
     }
 
-    async fn close(&self) {
+    async fn close(&self, reason: DisconnectReason) {
         self.closed.store(true, Ordering::Release);
         self.terminate_session().await;
-        let _ = self.engine_emitter.send(EngineEvent::Disconnected).await;
+        let _ = self
+            .engine_emitter
+            .send(EngineEvent::Disconnected { reason })
+            .await;
     }
 
     // Wait for the reconnection task to finish
@@ -382,9 +527,11 @@ impl EngineInner {
             Err(EngineError::Connection("engine is closed".to_owned()))?
         }
 
+        // Create the future before checking the flag so we can't miss the notification
+        let reconnected = self.reconnect_notifier.notified();
         if self.reconnecting.load(Ordering::Acquire) {
             // If currently reconnecting, wait for the reconnect task to finish
-            self.reconnect_notifier.notified().await;
+            reconnected.await;
         }
 
         // reconnect_task is finished here, so it is fine to try to read the RwLock here (should be a short lock)
@@ -406,12 +553,9 @@ impl EngineInner {
         }
 
         if self.reconnecting.load(Ordering::SeqCst) {
-            let inner = self.clone();
             if retry_now {
-                tokio::spawn(async move {
-                    inner.reconnect_interval.lock().await.reset(); // Retry directly
-                });
                 self.full_reconnect.store(full_reconnect, Ordering::Release);
+                self.retry_now.notify_one(); // Retry directly
             }
             return;
         }
@@ -422,7 +566,6 @@ impl EngineInner {
             let inner = self.clone();
             async move {
                 // Reconnetion logic
-                inner.reconnect_interval.lock().await.reset(); // Retry directly
                 inner.reconnecting.store(true, Ordering::Release);
                 inner
                     .full_reconnect
@@ -434,8 +577,8 @@ impl EngineInner {
                 if res.is_ok() {
                     log::warn!("RTCEngine successfully reconnected")
                 } else {
-                    log::error!("failed to reconnect after {} attemps", RECONNECT_ATTEMPTS);
-                    inner.close().await;
+                    log::error!("failed to reconnect: {:?}", res);
+                    inner.close(DisconnectReason::ReconnectExhausted).await;
                 }
 
                 inner.reconnect_notifier.notify_waiters();
@@ -444,27 +587,69 @@ impl EngineInner {
     }
 
     /// Runned every time the PeerConnection or the SignalClient is closed
-    /// We first try to resume the connection, if it fails, we start a full reconnect.
+    /// The ReconnectPolicy decides when to resume the session or start a full reconnect.
     async fn reconnect_task(self: &Arc<Self>) -> EngineResult<()> {
         // Get the latest connection info from the signal_client (including the refreshed token because the initial join token may have expired)
         let running_handle = self.running_handle.read().await;
         let signal_client = running_handle.as_ref().unwrap().session.signal_client();
         let url = signal_client.url();
-        let token = signal_client.token();
+        let mut token = signal_client.token();
         let options = signal_client.options();
         drop(running_handle);
 
-        for i in 0..RECONNECT_ATTEMPTS {
+        let started_at = Instant::now();
+        let mut resuming = false;
+        let mut restarting = false;
+
+        for i in 0.. {
+            let context = ReconnectContext {
+                attempt: i,
+                elapsed: started_at.elapsed(),
+                can_resume: !self.full_reconnect.load(Ordering::SeqCst),
+            };
+
+            let Some(mut attempt) = self.options.reconnect_policy.next_attempt(&context) else {
+                break;
+            };
+
+            if !context.can_resume {
+                attempt.mode = ReconnectMode::Restart;
+            }
+
+            let _ = self
+                .engine_emitter
+                .send(EngineEvent::ReconnectAttempt {
+                    attempt: i,
+                    mode: attempt.mode,
+                    delay: attempt.delay,
+                })
+                .await;
+
+            tokio::select! {
+                _ = sleep(attempt.delay) => {},
+                _ = self.retry_now.notified() => {},
+            }
+
             if self.closed.load(Ordering::Acquire) {
                 // The user closed the RTCEngine, cancel the reconnection task
                 return Ok(());
             }
 
-            if self.full_reconnect.load(Ordering::SeqCst) {
-                if i == 0 {
+            if attempt.mode == ReconnectMode::Restart || self.full_reconnect.load(Ordering::SeqCst)
+            {
+                // The current session is closed when restarting, it can't be resumed anymore
+                self.full_reconnect.store(true, Ordering::SeqCst);
+
+                if !restarting {
+                    restarting = true;
                     let _ = self.engine_emitter.send(EngineEvent::Restarting).await;
                 }
 
+                // A session created by a previous attempt may have received a newer token
+                if let Some(handle) = self.running_handle.read().await.as_ref() {
+                    token = handle.session.signal_client().token();
+                }
+
                 log::info!("restarting connection... attempt: {}", i);
                 if let Err(err) = self
                     .try_restart_connection(&url, &token, options.clone())
@@ -472,11 +657,14 @@ impl EngineInner {
                 {
                     log::error!("restarting connection failed: {}", err);
                 } else {
+                    // Requests sent from now on are meant for the new session
+                    self.full_reconnect.store(false, Ordering::Release);
                     let _ = self.engine_emitter.send(EngineEvent::Restarted).await;
                     return Ok(());
                 }
             } else {
-                if i == 0 {
+                if !resuming {
+                    resuming = true;
                     let _ = self.engine_emitter.send(EngineEvent::Resuming).await;
                 }
 
@@ -491,8 +679,6 @@ impl EngineInner {
                     return Ok(());
                 }
             }
-
-            self.reconnect_interval.lock().await.tick().await;
         }
 
         Err(EngineError::Connection("failed to reconnect".to_owned()))
@@ -2,12 +2,114 @@ use livekit_protocol as proto;
 use livekit_webrtc::prelude::*;
 use log::{debug, error};
 use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Weak};
 use std::time::Duration;
+use tokio::sync::{watch, Mutex as AsyncMutex};
 
-const _NEGOTIATION_FREQUENCY: Duration = Duration::from_millis(150);
+/// Negotiations requested within this interval are coalesced into a single offer
+const NEGOTIATION_FREQUENCY: Duration = Duration::from_millis(150);
 
 pub type OnOfferCreated = Box<dyn FnMut(SessionDescription) + Send + Sync>;
 
+/// Progress of the negotiations, identified by the generation of the last request they cover
+#[derive(Debug, Clone, Default)]
+struct NegotiationStatus {
+    answered: u64,
+    failed: Option<(u64, RtcErrorType, String)>,
+}
+
+/// Every negotiation request gets a generation, a request is satisfied once an answer to an
+/// offer created after it has been applied.
+/// Offers are only created in the stable state (a new offer is queued while one is pending),
+/// so an applied answer always answers the last offer created.
+#[derive(Debug)]
+struct NegotiationState {
+    requested: u64, // Generation of the last negotiation request
+    offered: u64,   // Generation covered by the last offer created
+    scheduled: bool,
+    status: watch::Sender<NegotiationStatus>,
+}
+
+impl Default for NegotiationState {
+    fn default() -> Self {
+        Self {
+            requested: 0,
+            offered: 0,
+            scheduled: false,
+            status: watch::channel(NegotiationStatus::default()).0,
+        }
+    }
+}
+
+impl NegotiationState {
+    /// Returns the generation of the request and whether a negotiation must be scheduled
+    /// (false when one is already scheduled, the requests are coalesced)
+    fn request(&mut self) -> (u64, bool) {
+        self.requested += 1;
+        let schedule = !self.scheduled;
+        self.scheduled = true;
+        (self.requested, schedule)
+    }
+
+    fn subscribe(&self) -> watch::Receiver<NegotiationStatus> {
+        self.status.subscribe()
+    }
+
+    fn debounce_elapsed(&mut self) {
+        self.scheduled = false;
+    }
+
+    /// The offer covers every negotiation requested until now
+    fn offer_created(&mut self) {
+        self.offered = self.requested;
+    }
+
+    /// Fail every request not answered yet, including the ones waiting for a queued offer
+    fn failed(&mut self, err: &RtcError) {
+        let generation = self.requested;
+        self.status.send_modify(|status| {
+            status.failed = Some((generation, err.error_type, err.message.clone()))
+        });
+    }
+
+    fn answer_applied(&mut self) {
+        let offered = self.offered;
+        self.status
+            .send_modify(|status| status.answered = status.answered.max(offered));
+    }
+}
+
+/// Resolves when the request `generation` has been answered
+async fn wait_answered(
+    mut status_rx: watch::Receiver<NegotiationStatus>,
+    generation: u64,
+) -> Result<(), RtcError> {
+    loop {
+        {
+            let status = status_rx.borrow_and_update();
+            if status.answered >= generation {
+                return Ok(());
+            }
+
+            if let Some((failed, error_type, message)) = &status.failed {
+                if *failed >= generation {
+                    return Err(RtcError {
+                        error_type: *error_type,
+                        message: message.clone(),
+                    });
+                }
+            }
+        }
+
+        if status_rx.changed().await.is_err() {
+            return Err(RtcError {
+                error_type: RtcErrorType::InvalidState,
+                message: "the transport has been dropped".to_owned(),
+            });
+        }
+    }
+}
+
 pub struct PeerTransport {
     signal_target: proto::SignalTarget,
     peer_connection: PeerConnection,
@@ -15,6 +117,7 @@ pub struct PeerTransport {
     on_offer_handler: Option<OnOfferCreated>,
     renegotiate: bool,
     restarting_ice: bool,
+    negotiation: NegotiationState,
 }
 
 impl Debug for PeerTransport {
@@ -34,6 +137,7 @@ impl PeerTransport {
             on_offer_handler: None,
             restarting_ice: false,
             renegotiate: false,
+            negotiation: NegotiationState::default(),
         }
     }
 
@@ -81,9 +185,23 @@ impl PeerTransport {
         &mut self,
         remote_description: SessionDescription,
     ) -> Result<(), RtcError> {
-        self.peer_connection
+        let is_answer = remote_description.sdp_type() == SdpType::Answer;
+        if let Err(err) = self
+            .peer_connection
             .set_remote_description(remote_description)
-            .await?;
+            .await
+        {
+            if is_answer {
+                // The queued offer would never be sent
+                self.renegotiate = false;
+                self.negotiation.failed(&err);
+            }
+            return Err(err);
+        }
+
+        if is_answer {
+            self.negotiation.answer_applied();
+        }
 
         for ic in self.pending_candidates.drain(..) {
             self.peer_connection.add_ice_candidate(ic).await?;
@@ -98,9 +216,38 @@ impl PeerTransport {
         Ok(())
     }
 
-    pub async fn negotiate(&mut self) -> Result<(), RtcError> {
-        // TODO(theomonnom) Debounce here with NEGOTIATION_FREQUENCY
-        self.create_and_send_offer(OfferOptions::default()).await
+    /// Request a negotiation of the transport, the requests received within NEGOTIATION_FREQUENCY
+    /// are coalesced into a single offer. If an offer is already pending (e.g. glare), a new offer
+    /// is sent once it is answered.
+    /// Resolves when an answer covering this request has been applied
+    pub async fn negotiate(transport: &Arc<AsyncMutex<Self>>) -> Result<(), RtcError> {
+        let (generation, status_rx) = {
+            let mut this = transport.lock().await;
+            let (generation, schedule) = this.negotiation.request();
+            if schedule {
+                tokio::spawn(Self::debounced_negotiation(Arc::downgrade(transport)));
+            }
+
+            (generation, this.negotiation.subscribe())
+        };
+
+        wait_answered(status_rx, generation).await
+    }
+
+    async fn debounced_negotiation(transport: Weak<AsyncMutex<Self>>) {
+        tokio::time::sleep(NEGOTIATION_FREQUENCY).await;
+        let Some(transport) = transport.upgrade() else {
+            return;
+        };
+
+        let mut this = transport.lock().await;
+        this.negotiation.debounce_elapsed();
+        if let Err(err) = this.create_and_send_offer(OfferOptions::default()).await {
+            error!(
+                "failed to negotiate the {:?} transport: {}",
+                this.signal_target, err
+            );
+        }
     }
 
     pub async fn create_anwser(
@@ -143,6 +290,19 @@ impl PeerTransport {
             }
         }
 
+        match self.send_offer(options).await {
+            Ok(()) => {
+                self.negotiation.offer_created();
+                Ok(())
+            }
+            Err(err) => {
+                self.negotiation.failed(&err);
+                Err(err)
+            }
+        }
+    }
+
+    async fn send_offer(&mut self, options: OfferOptions) -> Result<(), RtcError> {
         let offer = self.peer_connection.create_offer(options).await?;
         self.peer_connection
             .set_local_description(offer.clone())
@@ -151,3 +311,114 @@ impl PeerTransport {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    fn error() -> RtcError {
+        RtcError {
+            error_type: RtcErrorType::Internal,
+            message: "failed".to_owned(),
+        }
+    }
+
+    fn poll(state: &NegotiationState, generation: u64) -> Option<Result<(), RtcError>> {
+        wait_answered(state.subscribe(), generation).now_or_never()
+    }
+
+    #[test]
+    fn test_requests_are_coalesced() {
+        let mut state = NegotiationState::default();
+        assert_eq!(state.request(), (1, true));
+        assert_eq!(state.request(), (2, false));
+        assert_eq!(state.request(), (3, false));
+
+        state.debounce_elapsed();
+        state.offer_created();
+        assert!(poll(&state, 3).is_none());
+
+        state.answer_applied();
+        for generation in 1..=3 {
+            assert!(matches!(poll(&state, generation), Some(Ok(()))));
+        }
+
+        // A new request after the debounce schedules a new negotiation
+        assert_eq!(state.request(), (4, true));
+        assert!(poll(&state, 4).is_none());
+    }
+
+    #[test]
+    fn test_request_while_offer_pending() {
+        let mut state = NegotiationState::default();
+        state.request();
+        state.debounce_elapsed();
+        state.offer_created(); // Covers 1
+
+        // The offer is still pending, the new offer is queued until the answer
+        assert_eq!(state.request(), (2, true));
+        state.debounce_elapsed();
+
+        // The answer of the first offer can't satisfy the second request
+        state.answer_applied();
+        assert!(matches!(poll(&state, 1), Some(Ok(()))));
+        assert!(poll(&state, 2).is_none());
+
+        // Queued offer sent after the answer
+        state.offer_created();
+        state.answer_applied();
+        assert!(matches!(poll(&state, 2), Some(Ok(()))));
+    }
+
+    #[test]
+    fn test_duplicated_answer() {
+        let mut state = NegotiationState::default();
+        state.request();
+        state.offer_created();
+        state.answer_applied();
+
+        // Answers never mark the requests made after the last offer
+        state.request();
+        state.answer_applied();
+        assert!(poll(&state, 2).is_none());
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut state = NegotiationState::default();
+        state.request();
+        state.offer_created();
+        state.answer_applied();
+
+        state.request();
+        state.request();
+        state.failed(&error());
+        assert!(matches!(poll(&state, 1), Some(Ok(()))));
+        assert!(matches!(poll(&state, 2), Some(Err(_))));
+        assert!(matches!(poll(&state, 3), Some(Err(_))));
+
+        // The next requests can still succeed
+        state.request();
+        assert!(poll(&state, 4).is_none());
+        state.offer_created();
+        state.answer_applied();
+        assert!(matches!(poll(&state, 4), Some(Ok(()))));
+    }
+
+    #[tokio::test]
+    async fn test_wait_answered() {
+        let mut state = NegotiationState::default();
+        let (generation, _) = state.request();
+        let wait = tokio::spawn(wait_answered(state.subscribe(), generation));
+
+        state.offer_created();
+        state.answer_applied();
+        assert!(wait.await.unwrap().is_ok());
+
+        let (generation, _) = state.request();
+        let wait = tokio::spawn(wait_answered(state.subscribe(), generation));
+        drop(state);
+        assert!(wait.await.unwrap().is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{rtc_events, EngineError, EngineResult, SimulateScenario};
+use super::{rtc_events, ConnectPhase, EngineError, EngineOptions, EngineResult, SimulateScenario};
 use crate::options::TrackPublishOptions;
 use crate::prelude::TrackKind;
 use crate::rtc_engine::lk_runtime::LkRuntime;
@@ -6,7 +6,9 @@ use crate::rtc_engine::peer_transport::PeerTransport;
 use crate::rtc_engine::rtc_events::{RtcEvent, RtcEvents};
 use crate::track::LocalTrack;
 use crate::DataPacketKind;
-use livekit_api::signal_client::{SignalClient, SignalEvent, SignalEvents, SignalOptions};
+use livekit_api::signal_client::{
+    SignalClient, SignalError, SignalEvent, SignalEvents, SignalOptions,
+};
 use livekit_protocol as proto;
 use livekit_webrtc::prelude::*;
 use parking_lot::Mutex;
@@ -19,15 +21,18 @@ use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex as AsyncMutex;
-use tokio::sync::{mpsc, oneshot, watch};
+use tokio::sync::{mpsc, oneshot, watch, Notify};
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
-pub const ICE_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
 pub const TRACK_PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
 pub const LOSSY_DC_LABEL: &str = "_lossy";
 pub const RELIABLE_DC_LABEL: &str = "_reliable";
 
+/// Sending on the reliable data channel waits for the buffered amount to go below this threshold
+/// (Prevent the send buffer of the SCTP transport to be full)
+pub const RELIABLE_BUFFERED_AMOUNT_THRESHOLD: u64 = 2 * 1024 * 1024;
+
 pub type SessionEmitter = mpsc::UnboundedSender<SessionEvent>;
 pub type SessionEvents = mpsc::UnboundedReceiver<SessionEvent>;
 
@@ -39,12 +44,14 @@ pub enum SessionEvent {
     Data {
         participant_sid: String,
         payload: Vec<u8>,
+        topic: Option<String>,
         kind: DataPacketKind,
     },
     MediaTrack {
         track: MediaStreamTrack,
         stream: MediaStream,
         receiver: RtpReceiver,
+        transceiver: RtpTransceiver,
     },
     SpeakersChanged {
         speakers: Vec<proto::SpeakerInfo>,
@@ -52,6 +59,25 @@ pub enum SessionEvent {
     ConnectionQuality {
         updates: Vec<proto::ConnectionQualityInfo>,
     },
+    RefreshToken {
+        token: String,
+    },
+    RoomUpdate {
+        room: proto::Room,
+    },
+    SubscriptionPermissionUpdate {
+        update: proto::SubscriptionPermissionUpdate,
+    },
+    SubscribedQualityUpdate {
+        update: proto::SubscribedQualityUpdate,
+    },
+    LocalTrackMuted {
+        sid: String,
+        muted: bool,
+    },
+    LocalTrackUnpublished {
+        sid: String,
+    },
     // TODO(theomonnom): Move entirely the reconnection logic on mod.rs
     Close {
         source: String,
@@ -100,8 +126,11 @@ struct SessionInner {
     signal_client: Arc<SignalClient>,
     pc_state: AtomicU8, // PcState
     has_published: AtomicBool,
+    // The state of the primary peer connection is the state of the session.
+    // When the publisher is primary, it is negotiated as soon as the session is created
+    subscriber_primary: bool,
 
-    publisher_pc: AsyncMutex<PeerTransport>,
+    publisher_pc: Arc<AsyncMutex<PeerTransport>>,
     subscriber_pc: AsyncMutex<PeerTransport>,
 
     pending_tracks: Mutex<HashMap<String, oneshot::Sender<proto::TrackInfo>>>,
@@ -110,11 +139,15 @@ struct SessionInner {
     // used to send data to other participants (The SFU forwards the messages)
     lossy_dc: DataChannel,
     reliable_dc: DataChannel,
+    reliable_dc_drained: Arc<Notify>, // Notified when the buffered amount of reliable_dc changes
 
     // Keep a strong reference to the subscriber datachannels,
     // so we can receive data from other participants
     subscriber_dc: Mutex<Vec<DataChannel>>,
 
+    ice_timeout: Duration,
+    publisher_timeout: Duration,
+
     closed: AtomicBool,
     emitter: SessionEmitter,
 }
@@ -124,6 +157,7 @@ impl Debug for SessionInner {
         f.debug_struct("SessionInner")
             .field("pc_state", &self.pc_state)
             .field("has_published", &self.has_published)
+            .field("subscriber_primary", &self.subscriber_primary)
             .field("closed", &self.closed)
             .finish()
     }
@@ -146,30 +180,45 @@ impl RtcSession {
         url: &str,
         token: &str,
         options: SignalOptions,
+        engine_options: &EngineOptions,
     ) -> EngineResult<(Self, proto::JoinResponse, SessionEvents)> {
         let (session_emitter, session_events) = mpsc::unbounded_channel();
 
         let (signal_client, join_response, signal_events) =
-            SignalClient::connect(url, token, options).await?;
+            SignalClient::connect(url, token, options)
+                .await
+                .map_err(|err| match err {
+                    SignalError::ConnectTimeout => EngineError::Timeout(ConnectPhase::Signal),
+                    SignalError::JoinTimeout => EngineError::Timeout(ConnectPhase::Join),
+                    err => err.into(),
+                })?;
         let signal_client = Arc::new(signal_client);
         log::debug!("received JoinResponse: {:?}", join_response);
 
         let (rtc_emitter, rtc_events) = mpsc::unbounded_channel();
-        let rtc_config = RtcConfiguration {
-            ice_servers: {
-                let mut servers = vec![];
-                for ice_server in join_response.ice_servers.clone() {
-                    servers.push(IceServer {
+        // The ICE servers provided by the user are used in addition to the ones of the server
+        let mut rtc_config = engine_options.rtc_config.clone();
+        rtc_config
+            .ice_servers
+            .extend(
+                join_response
+                    .ice_servers
+                    .clone()
+                    .into_iter()
+                    .map(|ice_server| IceServer {
                         urls: ice_server.urls,
                         username: ice_server.username,
                         password: ice_server.credential,
-                    })
-                }
-                servers
-            },
-            continual_gathering_policy: ContinualGatheringPolicy::GatherContinually,
-            ice_transport_type: IceTransportsType::All,
-        };
+                    }),
+            );
+
+        if rtc_config.ice_transport_type == IceTransportsType::Relay
+            && !has_turn_server(&rtc_config.ice_servers)
+        {
+            return Err(EngineError::Connection(
+                "relay only ICE transport requires at least one TURN server".to_owned(),
+            ));
+        }
 
         let lk_runtime = LkRuntime::instance();
         let mut publisher_pc = PeerTransport::new(
@@ -209,17 +258,27 @@ impl RtcSession {
         rtc_events::forward_dc_events(&mut lossy_dc, rtc_emitter.clone());
         rtc_events::forward_dc_events(&mut reliable_dc, rtc_emitter.clone());
 
+        let reliable_dc_drained = Arc::new(Notify::new());
+        reliable_dc.on_buffered_amount_change(Some(Box::new({
+            let reliable_dc_drained = reliable_dc_drained.clone();
+            move |_| reliable_dc_drained.notify_waiters()
+        })));
+
         let (close_tx, close_rx) = watch::channel(false);
         let inner = Arc::new(SessionInner {
             pc_state: AtomicU8::new(PeerState::New as u8),
             has_published: Default::default(),
+            subscriber_primary: join_response.subscriber_primary,
             signal_client,
-            publisher_pc: AsyncMutex::new(publisher_pc),
+            publisher_pc: Arc::new(AsyncMutex::new(publisher_pc)),
             subscriber_pc: AsyncMutex::new(subscriber_pc),
             pending_tracks: Default::default(),
             lossy_dc,
             reliable_dc,
+            reliable_dc_drained,
             subscriber_dc: Default::default(),
+            ice_timeout: engine_options.ice_timeout,
+            publisher_timeout: engine_options.publisher_timeout,
             closed: Default::default(),
             emitter: session_emitter,
         });
@@ -235,6 +294,14 @@ impl RtcSession {
             rtc_task,
         };
 
+        // Otherwise the publisher is lazily negotiated when the first track or data packet is published
+        if !join_response.subscriber_primary || join_response.fast_publish {
+            if let Err(err) = inner.negotiate_publisher().await {
+                session.close().await;
+                return Err(err);
+            }
+        }
+
         Ok((session, join_response, session_events))
     }
 
@@ -281,6 +348,15 @@ impl RtcSession {
         self.inner.publish_data(data, kind).await
     }
 
+    #[inline]
+    pub async fn sync_state(
+        &self,
+        subscription: proto::UpdateSubscription,
+        publish_tracks: Vec<proto::TrackPublishedResponse>,
+    ) -> EngineResult<()> {
+        self.inner.sync_state(subscription, publish_tracks).await
+    }
+
     #[inline]
     pub async fn restart(&self) -> EngineResult<()> {
         self.inner.restart_session().await
@@ -292,7 +368,7 @@ impl RtcSession {
     }
 
     #[inline]
-    pub async fn simulate_scenario(&self, scenario: SimulateScenario) {
+    pub async fn simulate_scenario(&self, scenario: SimulateScenario) -> EngineResult<()> {
         self.inner.simulate_scenario(scenario).await
     }
 
@@ -418,7 +494,7 @@ impl SessionInner {
                             sdp: answer.to_string(),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             proto::signal_response::Message::Trickle(trickle) => {
                 let target = proto::SignalTarget::from_i32(trickle.target).unwrap();
@@ -473,6 +549,37 @@ impl SessionInner {
                     let _ = tx.send(publish_res.track.unwrap());
                 }
             }
+            proto::signal_response::Message::RefreshToken(token) => {
+                log::debug!("received a refreshed token");
+                self.signal_client.set_token(token.clone());
+                let _ = self.emitter.send(SessionEvent::RefreshToken { token });
+            }
+            proto::signal_response::Message::RoomUpdate(update) => {
+                if let Some(room) = update.room {
+                    let _ = self.emitter.send(SessionEvent::RoomUpdate { room });
+                }
+            }
+            proto::signal_response::Message::SubscriptionPermissionUpdate(update) => {
+                let _ = self
+                    .emitter
+                    .send(SessionEvent::SubscriptionPermissionUpdate { update });
+            }
+            proto::signal_response::Message::SubscribedQualityUpdate(update) => {
+                let _ = self
+                    .emitter
+                    .send(SessionEvent::SubscribedQualityUpdate { update });
+            }
+            proto::signal_response::Message::Mute(mute) => {
+                let _ = self.emitter.send(SessionEvent::LocalTrackMuted {
+                    sid: mute.sid,
+                    muted: mute.muted,
+                });
+            }
+            proto::signal_response::Message::TrackUnpublished(unpublished) => {
+                let _ = self.emitter.send(SessionEvent::LocalTrackUnpublished {
+                    sid: unpublished.track_sid,
+                });
+            }
 
             _ => {}
         }
@@ -497,15 +604,12 @@ impl SessionInner {
                             target: target as i32,
                         },
                     ))
-                    .await;
+                    .await?;
             }
             RtcEvent::ConnectionChange { state, target } => {
                 log::debug!("connection change, {:?} {:?}", state, target);
 
-                // The subscriber is always the primary peer connection
-                if target == proto::SignalTarget::Subscriber
-                    && state == PeerConnectionState::Connected
-                {
+                if target == self.primary_target() && state == PeerConnectionState::Connected {
                     let old_state = self
                         .pc_state
                         .swap(PeerState::Connected as u8, Ordering::SeqCst);
@@ -541,13 +645,13 @@ impl SessionInner {
                             sdp: offer.to_string(),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             RtcEvent::Track {
                 receiver,
                 mut streams,
                 track,
-                transceiver: _,
+                transceiver,
                 target: _,
             } => {
                 if !streams.is_empty() {
@@ -555,6 +659,7 @@ impl SessionInner {
                         stream: streams.remove(0),
                         track,
                         receiver,
+                        transceiver,
                     });
                 } else {
                     log::warn!("Track event with no streams");
@@ -573,6 +678,7 @@ impl SessionInner {
                         let _ = self.emitter.send(SessionEvent::Data {
                             participant_sid: user.participant_sid,
                             payload: user.payload,
+                            topic: user.topic,
                             kind: proto::data_packet::Kind::from_i32(data.kind)
                                 .unwrap()
                                 .into(),
@@ -598,9 +704,14 @@ impl SessionInner {
             pendings_tracks.insert(cid.clone(), tx);
         }
 
-        self.signal_client
+        if let Err(err) = self
+            .signal_client
             .send(proto::signal_request::Message::AddTrack(req))
-            .await;
+            .await
+        {
+            self.pending_tracks.lock().remove(&cid);
+            return Err(err.into());
+        }
 
         // Wait the result from the server (TrackInfo)
         tokio::select! {
@@ -710,12 +821,13 @@ impl SessionInner {
 
     async fn close(&self) {
         self.closed.store(true, Ordering::Release);
+        self.signal_client.clear_queue().await;
         self.signal_client.close().await;
         self.publisher_pc.lock().await.close();
         self.subscriber_pc.lock().await.close();
     }
 
-    async fn simulate_scenario(&self, scenario: SimulateScenario) {
+    async fn simulate_scenario(&self, scenario: SimulateScenario) -> EngineResult<()> {
         match scenario {
             SimulateScenario::SignalReconnect => {
                 self.signal_client.close().await;
@@ -727,7 +839,7 @@ impl SessionInner {
                             scenario: Some(proto::simulate_scenario::Scenario::SpeakerUpdate(3)),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             SimulateScenario::NodeFailure => {
                 self.signal_client
@@ -736,7 +848,7 @@ impl SessionInner {
                             scenario: Some(proto::simulate_scenario::Scenario::NodeFailure(true)),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             SimulateScenario::ServerLeave => {
                 self.signal_client
@@ -745,7 +857,7 @@ impl SessionInner {
                             scenario: Some(proto::simulate_scenario::Scenario::ServerLeave(true)),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             SimulateScenario::Migration => {
                 self.signal_client
@@ -754,7 +866,7 @@ impl SessionInner {
                             scenario: Some(proto::simulate_scenario::Scenario::Migration(true)),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             SimulateScenario::ForceTcp => {
                 self.signal_client
@@ -767,7 +879,7 @@ impl SessionInner {
                             ),
                         },
                     ))
-                    .await;
+                    .await?;
             }
             SimulateScenario::ForceTls => {
                 self.signal_client
@@ -780,9 +892,10 @@ impl SessionInner {
                             ),
                         },
                     ))
-                    .await;
+                    .await?;
             }
         }
+        Ok(())
     }
 
     async fn publish_data(
@@ -791,11 +904,39 @@ impl SessionInner {
         kind: DataPacketKind,
     ) -> Result<(), EngineError> {
         self.ensure_publisher_connected(kind).await?;
+        if kind == DataPacketKind::Reliable {
+            self.wait_reliable_dc_drained().await?;
+        }
+
         self.data_channel(kind)
             .send(&data.encode_to_vec(), true)
             .map_err(Into::into)
     }
 
+    /// Backpressure for the reliable data channel, wait until the buffered amount
+    /// goes below RELIABLE_BUFFERED_AMOUNT_THRESHOLD
+    async fn wait_reliable_dc_drained(&self) -> EngineResult<()> {
+        loop {
+            // Create the future before checking the buffered amount so we can't miss the notification
+            let drained = self.reliable_dc_drained.notified();
+            if self.reliable_dc.buffered_amount() <= RELIABLE_BUFFERED_AMOUNT_THRESHOLD {
+                return Ok(());
+            }
+
+            if self.reliable_dc.state() != DataState::Open {
+                Err(EngineError::Connection(
+                    "the reliable data channel is closed".to_owned(),
+                ))?
+            }
+
+            // Also poll in case the notification is never received (e.g. the dc is closing)
+            tokio::select! {
+                _ = drained => {},
+                _ = sleep(Duration::from_millis(100)) => {},
+            }
+        }
+    }
+
     /// Try to restart the session by doing an ICE Restart (The SignalClient is also restarted)
     /// This reconnection if more seemless compared to the full reconnection implemented in ['RTCEngine']
     async fn restart_session(&self) -> EngineResult<()> {
@@ -814,12 +955,61 @@ impl SessionInner {
         }
 
         self.wait_pc_connection().await?;
-        self.signal_client.flush_queue().await;
+        self.signal_client.flush_queue().await?;
+        Ok(())
+    }
+
+    /// Send our current state to the SFU (after a resume)
+    /// The server uses it to make sure we agree about the flowing tracks
+    async fn sync_state(
+        &self,
+        subscription: proto::UpdateSubscription,
+        publish_tracks: Vec<proto::TrackPublishedResponse>,
+    ) -> EngineResult<()> {
+        let answer = self
+            .subscriber_pc
+            .lock()
+            .await
+            .peer_connection()
+            .current_local_description()
+            .map(|answer| proto::SessionDescription {
+                r#type: "answer".to_string(),
+                sdp: answer.to_string(),
+            });
+
+        let mut data_channels = Vec::new();
+        for dc in [&self.lossy_dc, &self.reliable_dc] {
+            data_channels.extend(data_channel_info(
+                dc.label(),
+                dc.id(),
+                proto::SignalTarget::Publisher,
+            ));
+        }
+
+        for dc in self.subscriber_dc.lock().iter() {
+            data_channels.extend(data_channel_info(
+                dc.label(),
+                dc.id(),
+                proto::SignalTarget::Subscriber,
+            ));
+        }
+
+        let sync_state = proto::SyncState {
+            answer,
+            subscription: Some(subscription),
+            publish_tracks,
+            data_channels,
+        };
+
+        log::debug!("sending SyncState: {:?}", sync_state);
+        self.signal_client
+            .send(proto::signal_request::Message::SyncState(sync_state))
+            .await?;
         Ok(())
     }
 
     // Wait for PeerState to become PeerState::Connected
-    // Timeout after ['MAX_ICE_CONNECT_TIMEOUT']
+    // Timeout after ice_timeout
     async fn wait_pc_connection(&self) -> EngineResult<()> {
         let wait_connected = async move {
             while self.pc_state.load(Ordering::Acquire) != PeerState::Connected as u8 {
@@ -835,21 +1025,30 @@ impl SessionInner {
 
         tokio::select! {
             res = wait_connected => res,
-            _ = sleep(ICE_CONNECT_TIMEOUT) => {
-                let err = EngineError::Connection("wait_pc_connection timed out".to_string());
-                Err(err)
-            }
+            _ = sleep(self.ice_timeout) => Err(EngineError::Timeout(ConnectPhase::Ice)),
         }
     }
 
-    /// Start publisher negotiation
+    /// Start publisher negotiation and wait for the server answer
     async fn negotiate_publisher(&self) -> EngineResult<()> {
         self.has_published.store(true, Ordering::Release);
-        let res = self.publisher_pc.lock().await.negotiate().await;
+        let res = tokio::select! {
+            res = PeerTransport::negotiate(&self.publisher_pc) => res.map_err(Into::into),
+            _ = sleep(self.publisher_timeout) => Err(EngineError::Timeout(ConnectPhase::Publisher)),
+        };
+
         if let Err(err) = &res {
             log::error!("failed to negotiate the publisher: {:?}", err);
         }
-        res.map_err(Into::into)
+        res
+    }
+
+    fn primary_target(&self) -> proto::SignalTarget {
+        if self.subscriber_primary {
+            proto::SignalTarget::Subscriber
+        } else {
+            proto::SignalTarget::Publisher
+        }
     }
 
     /// Ensure the Publisher PC is connected, if not, start the negotiation
@@ -874,7 +1073,7 @@ impl SessionInner {
 
         // Wait until the PeerConnection is connected
         let wait_connected = async {
-            while self.publisher_pc.lock().await.is_connected() && dc.state() == DataState::Open {
+            while !self.publisher_pc.lock().await.is_connected() || dc.state() != DataState::Open {
                 if self.closed.load(Ordering::Acquire) {
                     return Err(EngineError::Connection("closed".to_string()));
                 }
@@ -887,9 +1086,9 @@ impl SessionInner {
 
         tokio::select! {
             res = wait_connected => res,
-            _ = sleep(ICE_CONNECT_TIMEOUT) => {
-                let err = EngineError::Connection("could not establish publisher connection: timeout".to_string());
-                log::error!("{}", err);
+            _ = sleep(self.publisher_timeout) => {
+                let err = EngineError::Timeout(ConnectPhase::Publisher);
+                log::error!("could not establish publisher connection: {}", err);
                 Err(err)
             }
         }
@@ -903,3 +1102,64 @@ impl SessionInner {
         }
     }
 }
+
+fn has_turn_server(ice_servers: &[IceServer]) -> bool {
+    ice_servers
+        .iter()
+        .flat_map(|ice_server| &ice_server.urls)
+        .any(|url| url.starts_with("turn:") || url.starts_with("turns:"))
+}
+
+/// The id of a data channel is -1 until it is negotiated, these are skipped
+fn data_channel_info(
+    label: String,
+    id: i32,
+    target: proto::SignalTarget,
+) -> Option<proto::DataChannelInfo> {
+    let Ok(id) = u32::try_from(id) else {
+        log::warn!("data channel {} isn't negotiated yet, skipping it", label);
+        return None;
+    };
+
+    Some(proto::DataChannelInfo {
+        label,
+        id,
+        target: target as i32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_turn_server() {
+        let ice_server = |url: &str| IceServer {
+            urls: vec![url.to_owned()],
+            username: "user".to_owned(),
+            password: "pass".to_owned(),
+        };
+
+        assert!(!has_turn_server(&[]));
+        assert!(!has_turn_server(&[ice_server(
+            "stun:stun.example.com:3478"
+        )]));
+        assert!(has_turn_server(&[
+            ice_server("stun:stun.example.com:3478"),
+            ice_server("turns:turn.example.com:443"),
+        ]));
+    }
+
+    #[test]
+    fn test_data_channel_info() {
+        let info =
+            data_channel_info("_reliable".to_owned(), 1, proto::SignalTarget::Publisher).unwrap();
+        assert_eq!(info.label, "_reliable");
+        assert_eq!(info.id, 1);
+        assert_eq!(info.target, proto::SignalTarget::Publisher as i32);
+
+        assert!(
+            data_channel_info("_lossy".to_owned(), -1, proto::SignalTarget::Subscriber).is_none()
+        );
+    }
+}
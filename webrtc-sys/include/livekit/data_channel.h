@@ -46,6 +46,8 @@ class DataChannel {
   void unregister_observer() const;
   bool send(const DataBuffer& buffer) const;
   rust::String label() const;
+  int id() const;
+  uint64_t buffered_amount() const;
   DataState state() const;
   void close() const;
 
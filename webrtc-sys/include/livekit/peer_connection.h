@@ -122,6 +122,10 @@ class PeerConnection {
 
   IceConnectionState ice_connection_state() const;
 
+  void get_stats(
+      rust::Box<AsyncContext> ctx,
+      rust::Fn<void(rust::Box<AsyncContext>, rust::String)> on_stats) const;
+
   void close() const;
 
  private:
@@ -199,10 +203,18 @@ class NativePeerConnectionObserver : public webrtc::PeerConnectionObserver {
   void OnInterestingUsage(int usage_pattern) override;
 
  private:
+  // May be null, see peer_connection_
+  rtc::scoped_refptr<webrtc::PeerConnectionInterface> stats_provider() const;
+
   friend PeerConnectionFactory;
   // The RtcRuntime is set inside PeerConnectionFactory, we can simplify that
   // once create_native_connection_observer is removed
   std::shared_ptr<RtcRuntime> rtc_runtime_;
+  // Also set inside PeerConnectionFactory, the senders/receivers need it to
+  // get their stats (Not refcounted, our PeerConnection owns both).
+  // It is null while CreatePeerConnectionOrError is running, the wrappers
+  // created by the callbacks fired at that time can't get their stats
+  webrtc::PeerConnectionInterface* peer_connection_ = nullptr;
   rust::Box<PeerConnectionObserverWrapper> observer_;
 };
 
@@ -18,6 +18,7 @@
 
 #include <memory>
 
+#include "api/peer_connection_interface.h"
 #include "api/rtp_receiver_interface.h"
 #include "livekit/helper.h"
 #include "livekit/media_stream.h"
@@ -36,8 +37,10 @@ namespace livekit {
 // TODO(theomonnom): FrameTransformer & FrameDecryptor interface
 class RtpReceiver {
  public:
-  RtpReceiver(std::shared_ptr<RtcRuntime> rtc_runtime,
-              rtc::scoped_refptr<webrtc::RtpReceiverInterface> receiver);
+  RtpReceiver(
+      std::shared_ptr<RtcRuntime> rtc_runtime,
+      rtc::scoped_refptr<webrtc::RtpReceiverInterface> receiver,
+      rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection);
 
   std::shared_ptr<MediaStreamTrack> track() const;
 
@@ -54,6 +57,10 @@ class RtpReceiver {
   void set_jitter_buffer_minimum_delay(bool is_some,
                                        double delay_seconds) const;
 
+  void get_stats(
+      rust::Box<ReceiverContext> ctx,
+      rust::Fn<void(rust::Box<ReceiverContext>, rust::String)> on_stats) const;
+
   rtc::scoped_refptr<webrtc::RtpReceiverInterface> rtc_receiver() const {
     return receiver_;
   }
@@ -61,6 +68,7 @@ class RtpReceiver {
  private:
   std::shared_ptr<RtcRuntime> rtc_runtime_;
   rtc::scoped_refptr<webrtc::RtpReceiverInterface> receiver_;
+  rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection_;
 };
 
 static std::shared_ptr<RtpReceiver> _shared_rtp_receiver() {
@@ -18,6 +18,7 @@
 
 #include <memory>
 
+#include "api/peer_connection_interface.h"
 #include "api/rtp_sender_interface.h"
 #include "livekit/media_stream.h"
 #include "livekit/rtc_error.h"
@@ -35,7 +36,8 @@ namespace livekit {
 class RtpSender {
  public:
   RtpSender(std::shared_ptr<RtcRuntime> rtc_runtime,
-            rtc::scoped_refptr<webrtc::RtpSenderInterface> sender);
+            rtc::scoped_refptr<webrtc::RtpSenderInterface> sender,
+            rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection);
 
   bool set_track(std::shared_ptr<MediaStreamTrack> track) const;
 
@@ -57,6 +59,10 @@ class RtpSender {
 
   void set_parameters(RtpParameters params) const;
 
+  void get_stats(
+      rust::Box<SenderContext> ctx,
+      rust::Fn<void(rust::Box<SenderContext>, rust::String)> on_stats) const;
+
   rtc::scoped_refptr<webrtc::RtpSenderInterface> rtc_sender() const {
     return sender_;
   }
@@ -64,6 +70,7 @@ class RtpSender {
  private:
   std::shared_ptr<RtcRuntime> rtc_runtime_;
   rtc::scoped_refptr<webrtc::RtpSenderInterface> sender_;
+  rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection_;
 };
 
 static std::shared_ptr<RtpSender> _shared_rtp_sender() {
@@ -18,6 +18,7 @@
 
 #include <memory>
 
+#include "api/peer_connection_interface.h"
 #include "api/rtp_parameters.h"
 #include "api/rtp_transceiver_direction.h"
 #include "api/rtp_transceiver_interface.h"
@@ -41,7 +42,8 @@ class RtpTransceiver {
  public:
   RtpTransceiver(
       std::shared_ptr<RtcRuntime> rtc_runtime,
-      rtc::scoped_refptr<webrtc::RtpTransceiverInterface> transceiver);
+      rtc::scoped_refptr<webrtc::RtpTransceiverInterface> transceiver,
+      rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection);
 
   MediaType media_type() const;
 
@@ -79,6 +81,7 @@ class RtpTransceiver {
  private:
   std::shared_ptr<RtcRuntime> rtc_runtime_;
   rtc::scoped_refptr<webrtc::RtpTransceiverInterface> transceiver_;
+  rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection_;
 };
 
 static std::shared_ptr<RtpTransceiver> _shared_rtp_transceiver() {
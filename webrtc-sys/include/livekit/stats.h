@@ -0,0 +1,48 @@
+/*
+ * Copyright 2023 LiveKit
+ *
+ * Licensed under the Apache License, Version 2.0 (the “License”);
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an “AS IS” BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#pragma once
+
+#include <memory>
+
+#include "api/stats/rtc_stats_collector_callback.h"
+#include "api/stats/rtc_stats_report.h"
+#include "rust/cxx.h"
+
+namespace livekit {
+
+// The report is serialized to JSON, it is then deserialized into typed structs
+// on the Rust side.
+// T is the Rust context type of the bridge calling GetStats
+template <typename T>
+class NativeRtcStatsCollector : public webrtc::RTCStatsCollectorCallback {
+ public:
+  NativeRtcStatsCollector(
+      rust::Box<T> ctx,
+      rust::Fn<void(rust::Box<T>, rust::String)> on_stats)
+      : ctx_(std::move(ctx)), on_stats_(on_stats) {}
+
+  void OnStatsDelivered(
+      const rtc::scoped_refptr<const webrtc::RTCStatsReport>& report) override {
+    on_stats_(std::move(ctx_), report->ToJson());
+  }
+
+ private:
+  rust::Box<T> ctx_;
+  rust::Fn<void(rust::Box<T>, rust::String)> on_stats_;
+};
+
+}  // namespace livekit
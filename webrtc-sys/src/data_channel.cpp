@@ -80,6 +80,14 @@ rust::String DataChannel::label() const {
   return data_channel_->label();
 }
 
+int DataChannel::id() const {
+  return data_channel_->id();
+}
+
+uint64_t DataChannel::buffered_amount() const {
+  return data_channel_->buffered_amount();
+}
+
 DataState DataChannel::state() const {
   return static_cast<DataState>(data_channel_->state());
 }
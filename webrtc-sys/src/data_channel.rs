@@ -52,6 +52,8 @@ pub mod ffi {
 
         fn send(self: &DataChannel, data: &DataBuffer) -> bool;
         fn label(self: &DataChannel) -> String;
+        fn id(self: &DataChannel) -> i32;
+        fn buffered_amount(self: &DataChannel) -> u64;
         fn state(self: &DataChannel) -> DataState;
         fn close(self: &DataChannel);
 
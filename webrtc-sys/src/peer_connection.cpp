@@ -25,6 +25,7 @@
 #include "livekit/media_stream.h"
 #include "livekit/rtc_error.h"
 #include "livekit/rtp_transceiver.h"
+#include "livekit/stats.h"
 #include "rtc_base/logging.h"
 #include "webrtc-sys/src/peer_connection.rs.h"
 #include "webrtc-sys/src/rtc_error.rs.h"
@@ -142,7 +143,8 @@ std::shared_ptr<RtpSender> PeerConnection::add_track(
     throw std::runtime_error(serialize_error(to_error(result.error())));
   }
 
-  return std::make_shared<RtpSender>(rtc_runtime_, result.value());
+  return std::make_shared<RtpSender>(rtc_runtime_, result.value(),
+                                     peer_connection_);
 }
 
 void PeerConnection::remove_track(std::shared_ptr<RtpSender> sender) const {
@@ -159,7 +161,8 @@ std::shared_ptr<RtpTransceiver> PeerConnection::add_transceiver(
   if (!result.ok())
     throw std::runtime_error(serialize_error(to_error(result.error())));
 
-  return std::make_shared<RtpTransceiver>(rtc_runtime_, result.value());
+  return std::make_shared<RtpTransceiver>(rtc_runtime_, result.value(),
+                                          peer_connection_);
 }
 
 std::shared_ptr<RtpTransceiver> PeerConnection::add_transceiver_for_media(
@@ -172,14 +175,15 @@ std::shared_ptr<RtpTransceiver> PeerConnection::add_transceiver_for_media(
   if (!result.ok())
     throw std::runtime_error(serialize_error(to_error(result.error())));
 
-  return std::make_shared<RtpTransceiver>(rtc_runtime_, result.value());
+  return std::make_shared<RtpTransceiver>(rtc_runtime_, result.value(),
+                                          peer_connection_);
 }
 
 rust::Vec<RtpSenderPtr> PeerConnection::get_senders() const {
   rust::Vec<RtpSenderPtr> vec;
   for (auto sender : peer_connection_->GetSenders())
-    vec.push_back(
-        RtpSenderPtr{std::make_shared<RtpSender>(rtc_runtime_, sender)});
+    vec.push_back(RtpSenderPtr{
+        std::make_shared<RtpSender>(rtc_runtime_, sender, peer_connection_)});
 
   return vec;
 }
@@ -187,8 +191,8 @@ rust::Vec<RtpSenderPtr> PeerConnection::get_senders() const {
 rust::Vec<RtpReceiverPtr> PeerConnection::get_receivers() const {
   rust::Vec<RtpReceiverPtr> vec;
   for (auto receiver : peer_connection_->GetReceivers())
-    vec.push_back(
-        RtpReceiverPtr{std::make_shared<RtpReceiver>(rtc_runtime_, receiver)});
+    vec.push_back(RtpReceiverPtr{std::make_shared<RtpReceiver>(
+        rtc_runtime_, receiver, peer_connection_)});
 
   return vec;
 }
@@ -196,8 +200,8 @@ rust::Vec<RtpReceiverPtr> PeerConnection::get_receivers() const {
 rust::Vec<RtpTransceiverPtr> PeerConnection::get_transceivers() const {
   rust::Vec<RtpTransceiverPtr> vec;
   for (auto transceiver : peer_connection_->GetTransceivers())
-    vec.push_back(RtpTransceiverPtr{
-        std::make_shared<RtpTransceiver>(rtc_runtime_, transceiver)});
+    vec.push_back(RtpTransceiverPtr{std::make_shared<RtpTransceiver>(
+        rtc_runtime_, transceiver, peer_connection_)});
 
   return vec;
 }
@@ -273,6 +277,15 @@ IceConnectionState PeerConnection::ice_connection_state() const {
       peer_connection_->ice_connection_state());
 }
 
+void PeerConnection::get_stats(
+    rust::Box<AsyncContext> ctx,
+    rust::Fn<void(rust::Box<AsyncContext>, rust::String)> on_stats) const {
+  auto observer =
+      rtc::make_ref_counted<NativeRtcStatsCollector<AsyncContext>>(
+          std::move(ctx), on_stats);
+  peer_connection_->GetStats(observer.get());
+}
+
 void PeerConnection::close() const {
   peer_connection_->Close();
 }
@@ -401,20 +414,30 @@ void NativePeerConnectionObserver::OnAddTrack(
         MediaStreamPtr{std::make_unique<MediaStream>(rtc_runtime_, item)});
   }
 
-  observer_->on_add_track(std::make_unique<RtpReceiver>(rtc_runtime_, receiver),
-                          std::move(vec));
+  observer_->on_add_track(
+      std::make_unique<RtpReceiver>(rtc_runtime_, receiver, stats_provider()),
+      std::move(vec));
 }
 
 void NativePeerConnectionObserver::OnTrack(
     rtc::scoped_refptr<webrtc::RtpTransceiverInterface> transceiver) {
-  observer_->on_track(
-      std::make_unique<RtpTransceiver>(rtc_runtime_, transceiver));
+  observer_->on_track(std::make_unique<RtpTransceiver>(
+      rtc_runtime_, transceiver, stats_provider()));
 }
 
 void NativePeerConnectionObserver::OnRemoveTrack(
     rtc::scoped_refptr<webrtc::RtpReceiverInterface> receiver) {
   observer_->on_remove_track(
-      std::make_unique<RtpReceiver>(rtc_runtime_, receiver));
+      std::make_unique<RtpReceiver>(rtc_runtime_, receiver, stats_provider()));
+}
+
+rtc::scoped_refptr<webrtc::PeerConnectionInterface>
+NativePeerConnectionObserver::stats_provider() const {
+  if (!peer_connection_) {
+    RTC_LOG(LS_WARNING) << "callback fired before the PeerConnection was "
+                           "created, stats won't be available";
+  }
+  return rtc::scoped_refptr<webrtc::PeerConnectionInterface>(peer_connection_);
 }
 
 void NativePeerConnectionObserver::OnInterestingUsage(int usage_pattern) {
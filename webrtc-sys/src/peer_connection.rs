@@ -181,6 +181,11 @@ pub mod ffi {
         fn signaling_state(self: &PeerConnection) -> SignalingState;
         fn ice_gathering_state(self: &PeerConnection) -> IceGatheringState;
         fn ice_connection_state(self: &PeerConnection) -> IceConnectionState;
+        fn get_stats(
+            self: &PeerConnection,
+            ctx: Box<AsyncContext>,
+            on_stats: fn(ctx: Box<AsyncContext>, json: String),
+        );
         fn close(self: &PeerConnection);
 
         fn _shared_peer_connection() -> SharedPtr<PeerConnection>; // Ignore
@@ -135,6 +135,7 @@ std::shared_ptr<PeerConnection> PeerConnectionFactory::create_peer_connection(
     throw std::runtime_error(serialize_error(to_error(result.error())));
   }
 
+  observer->peer_connection_ = result.value().get();  // See peer_connection.h
   return std::make_shared<PeerConnection>(rtc_runtime_, std::move(observer),
                                           result.value());
 }
@@ -19,13 +19,17 @@
 #include <memory>
 
 #include "absl/types/optional.h"
+#include "livekit/stats.h"
 
 namespace livekit {
 
 RtpReceiver::RtpReceiver(
     std::shared_ptr<RtcRuntime> rtc_runtime,
-    rtc::scoped_refptr<webrtc::RtpReceiverInterface> receiver)
-    : rtc_runtime_(rtc_runtime), receiver_(std::move(receiver)) {}
+    rtc::scoped_refptr<webrtc::RtpReceiverInterface> receiver,
+    rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection)
+    : rtc_runtime_(rtc_runtime),
+      receiver_(std::move(receiver)),
+      peer_connection_(std::move(peer_connection)) {}
 
 std::shared_ptr<MediaStreamTrack> RtpReceiver::track() const {
   return rtc_runtime_->get_or_create_media_stream_track(receiver_->track());
@@ -64,4 +68,18 @@ void RtpReceiver::set_jitter_buffer_minimum_delay(bool is_some,
       is_some ? absl::make_optional(delay_seconds) : absl::nullopt);
 }
 
+void RtpReceiver::get_stats(
+    rust::Box<ReceiverContext> ctx,
+    rust::Fn<void(rust::Box<ReceiverContext>, rust::String)> on_stats) const {
+  if (!peer_connection_) {
+    on_stats(std::move(ctx), "");  // Reported as unavailable in Rust
+    return;
+  }
+
+  auto observer =
+      rtc::make_ref_counted<NativeRtcStatsCollector<ReceiverContext>>(
+          std::move(ctx), on_stats);
+  peer_connection_->GetStats(receiver_, observer);
+}
+
 }  // namespace livekit
@@ -1,4 +1,5 @@
 use crate::impl_thread_safety;
+use std::any::Any;
 
 #[cxx::bridge(namespace = "livekit")]
 pub mod ffi {
@@ -28,9 +29,21 @@ pub mod ffi {
         fn id(self: &RtpReceiver) -> String;
         fn get_parameters(self: &RtpReceiver) -> RtpParameters;
         fn set_jitter_buffer_minimum_delay(self: &RtpReceiver, is_some: bool, delay_seconds: f64);
+        fn get_stats(
+            self: &RtpReceiver,
+            ctx: Box<ReceiverContext>,
+            on_stats: fn(ctx: Box<ReceiverContext>, json: String),
+        );
 
         fn _shared_rtp_receiver() -> SharedPtr<RtpReceiver>;
     }
+
+    extern "Rust" {
+        type ReceiverContext;
+    }
 }
 
+#[repr(transparent)]
+pub struct ReceiverContext(pub Box<dyn Any + Send>);
+
 impl_thread_safety!(ffi::RtpReceiver, Send + Sync);
@@ -16,11 +16,17 @@
 
 #include "livekit/rtp_sender.h"
 
+#include "livekit/stats.h"
+
 namespace livekit {
 
-RtpSender::RtpSender(std::shared_ptr<RtcRuntime> rtc_runtime,
-                     rtc::scoped_refptr<webrtc::RtpSenderInterface> sender)
-    : rtc_runtime_(rtc_runtime), sender_(std::move(sender)) {}
+RtpSender::RtpSender(
+    std::shared_ptr<RtcRuntime> rtc_runtime,
+    rtc::scoped_refptr<webrtc::RtpSenderInterface> sender,
+    rtc::scoped_refptr<webrtc::PeerConnectionInterface> peer_connection)
+    : rtc_runtime_(rtc_runtime),
+      sender_(std::move(sender)),
+      peer_connection_(std::move(peer_connection)) {}
 
 bool RtpSender::set_track(std::shared_ptr<MediaStreamTrack> track) const {
   return sender_->SetTrack(track->rtc_track().get());
@@ -72,4 +78,18 @@ void RtpSender::set_parameters(RtpParameters params) const {
     throw std::runtime_error(serialize_error(to_error(error)));
 }
 
+void RtpSender::get_stats(
+    rust::Box<SenderContext> ctx,
+    rust::Fn<void(rust::Box<SenderContext>, rust::String)> on_stats) const {
+  if (!peer_connection_) {
+    on_stats(std::move(ctx), "");  // Reported as unavailable in Rust
+    return;
+  }
+
+  auto observer =
+      rtc::make_ref_counted<NativeRtcStatsCollector<SenderContext>>(
+          std::move(ctx), on_stats);
+  peer_connection_->GetStats(sender_, observer);
+}
+
 }  // namespace livekit
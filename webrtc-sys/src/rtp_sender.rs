@@ -1,4 +1,5 @@
 use crate::impl_thread_safety;
+use std::any::Any;
 
 #[cxx::bridge(namespace = "livekit")]
 pub mod ffi {
@@ -29,9 +30,21 @@ pub mod ffi {
         fn init_send_encodings(self: &RtpSender) -> Vec<RtpEncodingParameters>;
         fn get_parameters(self: &RtpSender) -> RtpParameters;
         fn set_parameters(self: &RtpSender, parameters: RtpParameters) -> Result<()>;
+        fn get_stats(
+            self: &RtpSender,
+            ctx: Box<SenderContext>,
+            on_stats: fn(ctx: Box<SenderContext>, json: String),
+        );
 
         fn _shared_rtp_sender() -> SharedPtr<RtpSender>;
     }
+
+    extern "Rust" {
+        type SenderContext;
+    }
 }
 
+#[repr(transparent)]
+pub struct SenderContext(pub Box<dyn Any + Send>);
+
 impl_thread_safety!(ffi::RtpSender, Send + Sync);